@@ -0,0 +1,212 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Expr, Fields, Lit, LitStr, Meta};
+
+/// Derives `configgen_rs::Documented` from the doc comments of the fields of a struct
+///
+/// The `rename`, `rename_all`, `skip`, `skip_serializing` and `flatten` serde attributes are
+/// taken into account, so that the documentation is keyed by the names actually written in the
+/// configuration file. The documentation of the fields whose type is itself `Documented` is
+/// collected as well.
+#[proc_macro_derive(Documented)]
+pub fn derive_documented(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "Documented can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "Documented can only be derived for structs",
+            ))
+        }
+    };
+
+    let rename_all = SerdeAttributes::parse(&input.attrs)?.rename_all;
+    let mut pushes = vec![];
+    for field in fields {
+        let serde = SerdeAttributes::parse(&field.attrs)?;
+        if serde.skip {
+            continue;
+        }
+        let ty = &field.ty;
+        let nested = quote! {
+            (&::configgen_rs::traits::documented::NestedDocs::<#ty>::new()).nested_docs()
+        };
+        if serde.flatten {
+            pushes.push(quote! { docs.extend(#nested); });
+            continue;
+        }
+
+        let ident = field
+            .ident
+            .as_ref()
+            .expect("named fields have an identifier");
+        let name = match serde.rename {
+            Some(name) => name,
+            None => apply_rename_rule(
+                ident.to_string().trim_start_matches("r#"),
+                rename_all.as_deref(),
+            ),
+        };
+        if let Some(doc) = doc_comment(&field.attrs) {
+            pushes.push(quote! {
+                docs.push((::std::string::String::from(#name), ::std::string::String::from(#doc)));
+            });
+        }
+        pushes.push(quote! {
+            for (path, doc) in #nested {
+                docs.push((::std::format!("{}.{}", #name, path), doc));
+            }
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::configgen_rs::traits::Documented for #ident #ty_generics #where_clause {
+            fn field_docs() -> ::std::vec::Vec<(::std::string::String, ::std::string::String)> {
+                #[allow(unused_imports)]
+                use ::configgen_rs::traits::documented::{
+                    DocumentedNested as _, UndocumentedNested as _,
+                };
+                let mut docs = ::std::vec::Vec::new();
+                #(#pushes)*
+                docs
+            }
+        }
+    })
+}
+
+/// Joins the lines of the doc comments of an item, without the space following `///`
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(nv) => match &nv.value {
+                Expr::Lit(expr) => match &expr.lit {
+                    Lit::Str(s) => Some(s.value()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .flat_map(|doc| {
+            doc.split('\n')
+                .map(|line| line.strip_prefix(' ').unwrap_or(line).trim_end().to_owned())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let doc = lines.join("\n").trim_matches('\n').to_owned();
+    if doc.is_empty() {
+        None
+    } else {
+        Some(doc)
+    }
+}
+
+/// The serde attributes changing the keys written in the configuration file
+#[derive(Default)]
+struct SerdeAttributes {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    skip: bool,
+    flatten: bool,
+}
+
+impl SerdeAttributes {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut attributes = SerdeAttributes::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") || meta.path.is_ident("rename_all") {
+                    // Only the names used when serializing matter here
+                    let name = if meta.input.peek(syn::Token![=]) {
+                        Some(meta.value()?.parse::<LitStr>()?.value())
+                    } else {
+                        let mut name = None;
+                        meta.parse_nested_meta(|inner| {
+                            let value = inner.value()?.parse::<LitStr>()?.value();
+                            if inner.path.is_ident("serialize") {
+                                name = Some(value);
+                            }
+                            Ok(())
+                        })?;
+                        name
+                    };
+                    if meta.path.is_ident("rename") {
+                        attributes.rename = name.or(attributes.rename.take());
+                    } else {
+                        attributes.rename_all = name.or(attributes.rename_all.take());
+                    }
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                    attributes.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    attributes.flatten = true;
+                } else if meta.input.peek(syn::Token![=]) {
+                    meta.value()?.parse::<Expr>()?;
+                } else if meta.input.peek(syn::token::Paren) {
+                    meta.parse_nested_meta(|inner| {
+                        if inner.input.peek(syn::Token![=]) {
+                            inner.value()?.parse::<Expr>()?;
+                        }
+                        Ok(())
+                    })?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(attributes)
+    }
+}
+
+/// Applies a serde `rename_all` rule to a field name, written in snake case
+fn apply_rename_rule(field: &str, rule: Option<&str>) -> String {
+    let pascal_case = || {
+        field
+            .split('_')
+            .map(|word| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                    None => String::new(),
+                }
+            })
+            .collect::<String>()
+    };
+
+    match rule {
+        Some("lowercase") => field.to_ascii_lowercase(),
+        Some("UPPERCASE") | Some("SCREAMING_SNAKE_CASE") => field.to_ascii_uppercase(),
+        Some("PascalCase") => pascal_case(),
+        Some("camelCase") => {
+            let pascal = pascal_case();
+            let mut chars = pascal.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        }
+        Some("kebab-case") => field.replace('_', "-"),
+        Some("SCREAMING-KEBAB-CASE") => field.replace('_', "-").to_ascii_uppercase(),
+        _ => field.to_owned(),
+    }
+}
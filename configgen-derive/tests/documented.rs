@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use configgen_rs::initialization::{
+    initialize_documented_config_file, initialize_documented_config_file_with_options,
+    InitializationOptions,
+};
+use configgen_rs::{Documented, OverwritePolicy, SerializationFormat};
+use serde::Serialize;
+use temp_dir::TempDir;
+
+#[derive(Serialize, Documented)]
+struct Server {
+    /// Host name or address to listen on
+    pub host: String,
+    /// Port to listen on
+    ///
+    /// Below 1024, root is required
+    pub port: u16,
+}
+
+#[derive(Serialize, Documented)]
+struct Logging {
+    /// Verbosity of the logs
+    pub log_level: String,
+}
+
+#[derive(Serialize, Documented)]
+#[serde(rename_all = "camelCase")]
+struct AppConfig {
+    /// Name of the instance
+    pub instance_name: String,
+    /// The server the instance listens on
+    pub server: Server,
+    #[serde(rename = "fallback")]
+    /// Used when `server` is unreachable
+    pub fallback_server: Option<Server>,
+    /// Not written in the file
+    #[serde(skip)]
+    #[allow(dead_code)]
+    pub secret: String,
+    #[serde(flatten)]
+    pub logging: Logging,
+    pub undocumented: bool,
+}
+
+fn get_config() -> AppConfig {
+    AppConfig {
+        instance_name: "mine".to_owned(),
+        server: Server {
+            host: "localhost".to_owned(),
+            port: 8080,
+        },
+        fallback_server: None,
+        secret: "hunter2".to_owned(),
+        logging: Logging {
+            log_level: "info".to_owned(),
+        },
+        undocumented: false,
+    }
+}
+
+#[test]
+pub fn test_field_docs() {
+    let docs = AppConfig::field_docs();
+    let expected = vec![
+        ("instanceName", "Name of the instance"),
+        ("server", "The server the instance listens on"),
+        ("server.host", "Host name or address to listen on"),
+        (
+            "server.port",
+            "Port to listen on\n\nBelow 1024, root is required",
+        ),
+        ("fallback", "Used when `server` is unreachable"),
+        ("fallback.host", "Host name or address to listen on"),
+        (
+            "fallback.port",
+            "Port to listen on\n\nBelow 1024, root is required",
+        ),
+        ("log_level", "Verbosity of the logs"),
+    ];
+    let docs: Vec<(&str, &str)> = docs
+        .iter()
+        .map(|(path, doc)| (path.as_str(), doc.as_str()))
+        .collect();
+    assert_eq!(docs, expected);
+}
+
+#[test]
+pub fn test_initialize_documented_config_file_toml() {
+    let tmpdir = TempDir::new().unwrap();
+    let path = tmpdir.child("config.toml");
+    initialize_documented_config_file(
+        &get_config(),
+        &path,
+        SerializationFormat::Toml,
+        OverwritePolicy::Fail,
+    )
+    .unwrap();
+
+    let expected = r#"# Name of the instance
+instanceName = "mine"
+# Verbosity of the logs
+log_level = "info"
+undocumented = false
+
+# The server the instance listens on
+[server]
+# Host name or address to listen on
+host = "localhost"
+# Port to listen on
+#
+# Below 1024, root is required
+port = 8080
+"#;
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
+}
+
+#[test]
+pub fn test_initialize_documented_config_file_json_sidecar() {
+    let tmpdir = TempDir::new().unwrap();
+    let path = tmpdir.child("config.json");
+    let options = InitializationOptions {
+        docs_sidecar: true,
+        ..Default::default()
+    };
+    initialize_documented_config_file_with_options(
+        &get_config(),
+        &path,
+        SerializationFormat::Json,
+        OverwritePolicy::Fail,
+        &options,
+    )
+    .unwrap();
+
+    let data = std::fs::read_to_string(&path).unwrap();
+    assert!(!data.contains("Name of the instance"));
+    let sidecar = std::fs::read_to_string(tmpdir.child("config.docs.json")).unwrap();
+    let docs: HashMap<String, String> = serde_json::from_str(&sidecar).unwrap();
+    assert_eq!(
+        docs["server.port"],
+        "Port to listen on\n\nBelow 1024, root is required"
+    );
+}
@@ -0,0 +1,262 @@
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::formats;
+use crate::initialization::{self, write_config_file, InitializationOptions};
+use crate::Error as ConfiggenError;
+use crate::{DefaultConfig, InitializationOutcome, OverwritePolicy, SerializationFormat, Validate};
+
+/// Same as `initialization::create_config_dir`, the directory being created on the blocking thread
+/// pool of tokio so that the runtime is not blocked
+pub async fn create_config_dir(dir_to_create: PathBuf) -> Result<(), ConfiggenError> {
+    run_blocking(move || initialization::create_config_dir(dir_to_create)).await
+}
+
+/// Same as `initialization::initialize_config_file`, without blocking the runtime
+///
+/// `config` is serialized on the calling task, only the file system being accessed on the
+/// blocking thread pool.
+pub async fn initialize_config_file(
+    config: &(impl DefaultConfig + Serialize),
+    config_file_path: &Path,
+    format: SerializationFormat,
+    policy: OverwritePolicy,
+) -> Result<InitializationOutcome, ConfiggenError> {
+    initialize_config_file_with_options(
+        config,
+        config_file_path,
+        format,
+        policy,
+        &InitializationOptions::default(),
+    )
+    .await
+}
+
+/// Same as `initialization::initialize_config_file_with_options`, without blocking the runtime
+pub async fn initialize_config_file_with_options(
+    config: &(impl DefaultConfig + Serialize),
+    config_file_path: &Path,
+    format: SerializationFormat,
+    policy: OverwritePolicy,
+    options: &InitializationOptions,
+) -> Result<InitializationOutcome, ConfiggenError> {
+    let data = initialization::serialize_config(config, config_file_path, format, None, options)?;
+    // The defaults merged by `OverwritePolicy::Merge` have to outlive the borrow of `config`. Its
+    // `None` fields are dropped, as the serializers of the typed `config` skip them
+    let defaults =
+        serde_json::to_value(config).map_err(|e| ConfiggenError::SerializationFailed {
+            format,
+            source: Box::new(e),
+        })?;
+    let defaults = formats::without_null_entries(&defaults);
+
+    let config_file_path = config_file_path.to_path_buf();
+    let options = options.clone();
+    run_blocking(move || {
+        write_config_file(
+            &defaults,
+            &data,
+            &config_file_path,
+            format,
+            policy,
+            &options,
+        )
+    })
+    .await
+}
+
+/// Same as `initialization::load_config`, without blocking the runtime
+pub async fn load_config<T: DeserializeOwned + Send + 'static>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+) -> Result<T, ConfiggenError> {
+    let config_file_path = config_file_path.to_path_buf();
+    run_blocking(move || initialization::load_config(&config_file_path, format)).await
+}
+
+/// Same as `initialization::load_validated_config`, without blocking the runtime
+pub async fn load_validated_config<T: DeserializeOwned + Validate + Send + 'static>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+) -> Result<T, ConfiggenError> {
+    let config_file_path = config_file_path.to_path_buf();
+    run_blocking(move || initialization::load_validated_config(&config_file_path, format)).await
+}
+
+/// Same as `initialization::load_or_init`, without blocking the runtime
+pub async fn load_or_init<T: DefaultConfig + Serialize + DeserializeOwned + Send + 'static>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+    default: &T,
+) -> Result<(T, bool), ConfiggenError> {
+    let outcome =
+        initialize_config_file(default, config_file_path, format, OverwritePolicy::Skip).await?;
+    let created = outcome == InitializationOutcome::Created;
+
+    let config = load_config(config_file_path, format).await?;
+    Ok((config, created))
+}
+
+/// Same as `initialization::load_or_init_validated`, without blocking the runtime
+pub async fn load_or_init_validated<T>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+    default: &T,
+) -> Result<(T, bool), ConfiggenError>
+where
+    T: DefaultConfig + Validate + Serialize + DeserializeOwned + Send + 'static,
+{
+    let violations = default.validate();
+    if !violations.is_empty() {
+        return Err(ConfiggenError::ValidationFailed {
+            path: config_file_path.to_path_buf(),
+            violations,
+        });
+    }
+    let outcome =
+        initialize_config_file(default, config_file_path, format, OverwritePolicy::Skip).await?;
+    let created = outcome == InitializationOutcome::Created;
+
+    let config = load_validated_config(config_file_path, format).await?;
+    Ok((config, created))
+}
+
+/// Runs `f` on the blocking thread pool, propagating its panics as the synchronous functions would
+async fn run_blocking<R, F>(f: F) -> R
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(r) => r,
+        Err(e) => match e.try_into_panic() {
+            Ok(panic) => std::panic::resume_unwind(panic),
+            // Only happens if the runtime is shutting down
+            Err(e) => panic!("{}", e),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{get_server_config, ServerConfig};
+    use crate::utils::read_configuration;
+    use temp_dir::TempDir;
+
+    #[tokio::test]
+    pub async fn test_create_config_dir() {
+        let tmpdir = TempDir::new().unwrap();
+        let dir = tmpdir.path().join("parent").join("app");
+        create_config_dir(dir.clone()).await.unwrap();
+        assert!(dir.is_dir());
+
+        let r = create_config_dir(dir).await;
+        assert!(matches!(
+            r,
+            Err(ConfiggenError::ConfigDirectoryAlreadyExists { .. })
+        ));
+    }
+
+    #[tokio::test]
+    pub async fn test_initialize_config_file() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("config.ron");
+        let outcome = initialize_config_file(
+            &get_server_config(),
+            &path,
+            SerializationFormat::Ron,
+            OverwritePolicy::Fail,
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome, InitializationOutcome::Created);
+        // Same output as the synchronous function
+        assert_eq!(
+            read_configuration(&path).unwrap(),
+            formats::to_string(&get_server_config(), SerializationFormat::Ron).unwrap()
+        );
+
+        let r = initialize_config_file(
+            &get_server_config(),
+            &path,
+            SerializationFormat::Ron,
+            OverwritePolicy::Fail,
+        )
+        .await;
+        assert!(matches!(
+            r,
+            Err(ConfiggenError::ConfigFileAlreadyExists { .. })
+        ));
+
+        std::fs::write(&path, "(name: \"mine\")").unwrap();
+        let outcome = initialize_config_file(
+            &get_server_config(),
+            &path,
+            SerializationFormat::Ron,
+            OverwritePolicy::Merge,
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome, InitializationOutcome::Merged);
+        let config: ServerConfig = load_config(&path, SerializationFormat::Ron).await.unwrap();
+        assert_eq!(config.name, "mine");
+        assert_eq!(config.server, get_server_config().server);
+    }
+
+    #[tokio::test]
+    pub async fn test_merge_policy_toml_with_none() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("config.toml");
+        std::fs::write(&path, "name = \"x\"\n").unwrap();
+
+        let outcome = initialize_config_file(
+            &get_server_config(),
+            &path,
+            SerializationFormat::Toml,
+            OverwritePolicy::Merge,
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome, InitializationOutcome::Merged);
+        let config: ServerConfig = load_config(&path, SerializationFormat::Toml).await.unwrap();
+        assert_eq!(config.name, "x");
+        assert_eq!(config.timeout, None);
+        assert_eq!(config.server, get_server_config().server);
+    }
+
+    #[tokio::test]
+    pub async fn test_load_or_init() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("config.toml");
+
+        let (config, created) =
+            load_or_init(&path, SerializationFormat::Toml, &get_server_config())
+                .await
+                .unwrap();
+        assert!(created);
+        assert_eq!(config, get_server_config());
+
+        std::fs::write(
+            &path,
+            "name = \"app\"\n[server]\nhost = \"localhost\"\nport = 0\n",
+        )
+        .unwrap();
+        let (_, created) = load_or_init(&path, SerializationFormat::Toml, &get_server_config())
+            .await
+            .unwrap();
+        assert!(!created);
+        let r = load_validated_config::<ServerConfig>(&path, SerializationFormat::Toml).await;
+        assert!(matches!(r, Err(ConfiggenError::ValidationFailed { .. })));
+        let r =
+            load_or_init_validated(&path, SerializationFormat::Toml, &get_server_config()).await;
+        assert!(matches!(r, Err(ConfiggenError::ValidationFailed { .. })));
+
+        let r =
+            load_config::<ServerConfig>(&tmpdir.child("missing.toml"), SerializationFormat::Toml)
+                .await;
+        assert!(matches!(r, Err(ConfiggenError::LoadingFailed { .. })));
+    }
+}
@@ -0,0 +1,489 @@
+//! Command-line interface to configgen-rs, to create, inspect and convert configuration files
+//! without writing any Rust
+//!
+//! Every subcommand accepts `--json`, printing a single JSON object on stdout instead of text, e.g.
+//! `{"ok": false, "error": {"kind": "ParsingFailed", "message": "..."}}`. The exit codes follow
+//! the `sysexits.h` conventions (see `exit_code`), `diff` exiting with 1 when the files differ.
+
+// The errors of the library are passed along as they are, to be mapped to exit codes in one place
+#![allow(clippy::result_large_err)]
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::{json, Map, Value};
+
+use configgen_rs::conversion::convert_config_file;
+use configgen_rs::diff::{diff_config_file, Change};
+use configgen_rs::environment::EnvOverrides;
+use configgen_rs::initialization::initialize_config_file;
+use configgen_rs::paths::AppPaths;
+use configgen_rs::utils::{missing_keys, read_value, value_to_string};
+use configgen_rs::SerializationFormat;
+use configgen_rs::{Error as ConfiggenError, InitializationOutcome, OverwritePolicy};
+
+#[derive(Parser)]
+#[command(
+    name = "configgen",
+    version,
+    about = "Create, inspect and convert configuration files"
+)]
+struct Cli {
+    /// Print the result as a JSON object on stdout, for scripts and CI
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a configuration file from a template holding its default values
+    Init {
+        /// The configuration file to create
+        path: PathBuf,
+        /// The file holding the default configuration
+        #[arg(long)]
+        from: PathBuf,
+        /// The format of the default configuration, inferred from its extension by default
+        #[arg(long, value_parser = parse_format)]
+        from_format: Option<SerializationFormat>,
+        /// The format of the created file, inferred from its extension by default
+        #[arg(long, value_parser = parse_format)]
+        format: Option<SerializationFormat>,
+        /// What to do if the configuration file already exists
+        #[arg(long, value_enum, default_value_t = Policy::Fail)]
+        policy: Policy,
+    },
+    /// Print a configuration file, possibly overridden by environment variables
+    Show {
+        path: PathBuf,
+        /// The format of the file, inferred from its extension by default
+        #[arg(long, value_parser = parse_format)]
+        format: Option<SerializationFormat>,
+        /// The format to print the configuration in, the one of the file by default
+        #[arg(long, value_parser = parse_format)]
+        to: Option<SerializationFormat>,
+        /// Apply the environment variables starting with this prefix (e.g. `MYAPP__SERVER__PORT`)
+        #[arg(long, value_name = "PREFIX")]
+        env: Option<String>,
+    },
+    /// Check that a configuration file can be parsed, and that it has all the default keys
+    Validate {
+        path: PathBuf,
+        /// The format of the file, inferred from its extension by default
+        #[arg(long, value_parser = parse_format)]
+        format: Option<SerializationFormat>,
+        /// The file holding the default configuration, whose keys must all be in the file
+        #[arg(long, value_name = "DEFAULT")]
+        against: Option<PathBuf>,
+        /// The format of the default configuration, inferred from its extension by default
+        #[arg(long, value_parser = parse_format)]
+        against_format: Option<SerializationFormat>,
+    },
+    /// Write a configuration file in another format
+    Convert {
+        input: PathBuf,
+        output: PathBuf,
+        /// The format of the input file, inferred from its extension by default
+        #[arg(long, value_parser = parse_format)]
+        from: Option<SerializationFormat>,
+        /// The format of the output file, inferred from its extension by default
+        #[arg(long, value_parser = parse_format)]
+        to: Option<SerializationFormat>,
+        /// What to do if the output file already exists
+        #[arg(long, value_enum, default_value_t = Policy::Fail)]
+        policy: Policy,
+    },
+    /// List the keys of a configuration file that differ from the default configuration
+    Diff {
+        /// The file holding the default configuration
+        default: PathBuf,
+        /// The configuration file to compare with the default one
+        config: PathBuf,
+        /// The format of the default configuration, inferred from its extension by default
+        #[arg(long, value_parser = parse_format)]
+        default_format: Option<SerializationFormat>,
+        /// The format of the configuration file, inferred from its extension by default
+        #[arg(long, value_parser = parse_format)]
+        format: Option<SerializationFormat>,
+        /// Print the differences as a JSON Patch (RFC 6902) turning the default into the file
+        #[arg(long)]
+        patch: bool,
+    },
+    /// Print the standard path of the configuration file of an application
+    Path {
+        /// The name of the application
+        #[arg(long)]
+        app: String,
+        /// The reverse domain name qualifier of the application, only used on macOS
+        #[arg(long, default_value = "")]
+        qualifier: String,
+        /// The organization developing the application, unused on Linux
+        #[arg(long, default_value = "")]
+        organization: String,
+        /// The name of the configuration file, without its extension
+        #[arg(long, default_value = "config")]
+        file: String,
+        #[arg(long, value_parser = parse_format, default_value = "toml")]
+        format: SerializationFormat,
+        /// Print a directory of the application instead of the path of the configuration file
+        #[arg(long, value_enum)]
+        dir: Option<Dir>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Policy {
+    Fail,
+    Skip,
+    Overwrite,
+    Backup,
+    Merge,
+}
+
+impl From<Policy> for OverwritePolicy {
+    fn from(policy: Policy) -> Self {
+        match policy {
+            Policy::Fail => OverwritePolicy::Fail,
+            Policy::Skip => OverwritePolicy::Skip,
+            Policy::Overwrite => OverwritePolicy::Overwrite,
+            Policy::Backup => OverwritePolicy::BackupThenOverwrite,
+            Policy::Merge => OverwritePolicy::Merge,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Dir {
+    Config,
+    Data,
+    Cache,
+    State,
+}
+
+/// What a successful subcommand prints, as text and as JSON
+struct Report {
+    text: String,
+    json: Map<String, Value>,
+    exit_code: u8,
+}
+
+impl Report {
+    fn new(text: String, json: Value) -> Self {
+        let json = match json {
+            Value::Object(json) => json,
+            _ => unreachable!("reports are JSON objects"),
+        };
+        Report {
+            text,
+            json,
+            exit_code: 0,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command) {
+        Ok(report) => {
+            if cli.json {
+                let mut json = Map::new();
+                json.insert("ok".to_owned(), Value::Bool(true));
+                json.extend(report.json);
+                println!("{}", Value::Object(json));
+            } else if !report.text.is_empty() {
+                println!("{}", report.text.trim_end());
+            }
+            ExitCode::from(report.exit_code)
+        }
+        Err(e) => {
+            let (kind, code) = exit_code(&e);
+            if cli.json {
+                let mut error = json!({ "kind": kind, "message": error_message(&e) });
+                if let Some(values) = offending_values(&e) {
+                    error["values"] = values;
+                }
+                let json = json!({ "ok": false, "error": error });
+                println!("{}", json);
+            } else {
+                eprintln!("configgen: {}", error_message(&e));
+            }
+            ExitCode::from(code)
+        }
+    }
+}
+
+fn run(command: Command) -> Result<Report, ConfiggenError> {
+    match command {
+        Command::Init {
+            path,
+            from,
+            from_format,
+            format,
+            policy,
+        } => {
+            let template = read_value(&from, format_of(&from, from_format)?)?;
+            let format = format_of(&path, format)?;
+            let outcome = initialize_config_file(&template, &path, format, policy.into())?;
+            let backup = match &outcome {
+                InitializationOutcome::BackedUp(backup) => Some(backup.display().to_string()),
+                _ => None,
+            };
+            let (outcome, action) = match outcome {
+                InitializationOutcome::Created => ("created", "Created"),
+                InitializationOutcome::Skipped => ("skipped", "Skipped"),
+                InitializationOutcome::Overwritten => ("overwritten", "Overwrote"),
+                InitializationOutcome::BackedUp(_) => ("backed_up", "Backed up and overwrote"),
+                InitializationOutcome::Merged => ("merged", "Merged the defaults into"),
+            };
+            let text = format!("{} {}", action, path.display());
+            Ok(Report::new(
+                text,
+                json!({
+                    "path": path.display().to_string(),
+                    "outcome": outcome,
+                    "backup": backup,
+                }),
+            ))
+        }
+        Command::Show {
+            path,
+            format,
+            to,
+            env,
+        } => {
+            let format = format_of(&path, format)?;
+            let mut config = read_value(&path, format)?;
+            let overridden = match env {
+                Some(prefix) => EnvOverrides::new(&prefix).apply(
+                    &mut config,
+                    std::env::vars_os().filter_map(|(name, value)| {
+                        Some((name.into_string().ok()?, value.into_string().ok()?))
+                    }),
+                )?,
+                None => vec![],
+            };
+            let text = value_to_string(&config, to.unwrap_or(format))?;
+            Ok(Report::new(
+                text,
+                json!({ "config": config, "overridden": overridden }),
+            ))
+        }
+        Command::Validate {
+            path,
+            format,
+            against,
+            against_format,
+        } => {
+            let config = read_value(&path, format_of(&path, format)?)?;
+            let missing = match against {
+                Some(default) => {
+                    let default = read_value(&default, format_of(&default, against_format)?)?;
+                    missing_keys(&config, &default)
+                }
+                None => vec![],
+            };
+            let text = match missing.is_empty() {
+                true => format!("{} is valid", path.display()),
+                false => format!("{} lacks the keys {}", path.display(), missing.join(", ")),
+            };
+            let mut report = Report::new(
+                text,
+                json!({ "valid": missing.is_empty(), "missing": missing }),
+            );
+            if !missing.is_empty() {
+                report.exit_code = DATA_ERROR;
+            }
+            Ok(report)
+        }
+        Command::Convert {
+            input,
+            output,
+            from,
+            to,
+            policy,
+        } => {
+            let from = format_of(&input, from)?;
+            let to = format_of(&output, to)?;
+            let outcome = convert_config_file(&input, from, &output, to, policy.into())?;
+            Ok(Report::new(
+                format!("Converted {} to {}", input.display(), output.display()),
+                json!({
+                    "path": output.display().to_string(),
+                    "format": to.to_string(),
+                    "written": outcome != InitializationOutcome::Skipped,
+                }),
+            ))
+        }
+        Command::Diff {
+            default,
+            config,
+            default_format,
+            format,
+            patch,
+        } => {
+            let default = read_value(&default, format_of(&default, default_format)?)?;
+            let diff = diff_config_file(&default, &config, format_of(&config, format)?)?;
+            let text = match patch {
+                true => serde_json::to_string_pretty(&diff.to_json_patch())
+                    .expect("JSON values can always be serialized"),
+                false => diff.to_string(),
+            };
+            let changes: Vec<Value> = diff
+                .changes()
+                .iter()
+                .map(|change| match change {
+                    Change::Added { value, .. } => {
+                        json!({ "op": "added", "path": change.dotted_path(), "new": value })
+                    }
+                    Change::Removed { value, .. } => {
+                        json!({ "op": "removed", "path": change.dotted_path(), "old": value })
+                    }
+                    Change::Changed { old, new, .. } => json!({
+                        "op": "changed",
+                        "path": change.dotted_path(),
+                        "old": old,
+                        "new": new,
+                    }),
+                })
+                .collect();
+            let mut report = Report::new(
+                text,
+                json!({ "changes": changes, "patch": diff.to_json_patch() }),
+            );
+            if !diff.is_empty() {
+                report.exit_code = 1;
+            }
+            Ok(report)
+        }
+        Command::Path {
+            app,
+            qualifier,
+            organization,
+            file,
+            format,
+            dir,
+        } => {
+            let paths = AppPaths::new(&qualifier, &organization, &app);
+            let path = match dir {
+                None => paths.config_file_path(&file, format)?,
+                Some(Dir::Config) => paths.config_dir()?,
+                Some(Dir::Data) => paths.data_dir()?,
+                Some(Dir::Cache) => paths.cache_dir()?,
+                Some(Dir::State) => paths.state_dir()?,
+            };
+            let path = path.display().to_string();
+            Ok(Report::new(path.clone(), json!({ "path": path })))
+        }
+    }
+}
+
+fn parse_format(s: &str) -> Result<SerializationFormat, ConfiggenError> {
+    s.parse()
+}
+
+/// Returns `format` if given, and the format matching the extension of `path` otherwise
+fn format_of(
+    path: &Path,
+    format: Option<SerializationFormat>,
+) -> Result<SerializationFormat, ConfiggenError> {
+    match format {
+        Some(format) => Ok(format),
+        None => SerializationFormat::from_path(path),
+    }
+}
+
+/// The message of `e` followed by the ones of its sources, most errors of the library wrapping the
+/// actual cause
+fn error_message(e: &ConfiggenError) -> String {
+    let mut message = e.to_string();
+    let mut source = std::error::Error::source(e);
+    while let Some(e) = source {
+        message.push_str(&format!(": {}", e));
+        source = e.source();
+    }
+    message
+}
+
+/// The values reported by `e`, if it is about specific values of the configuration
+fn offending_values(e: &ConfiggenError) -> Option<Value> {
+    let values = match e {
+        ConfiggenError::ValidationFailed { violations, .. } => violations,
+        ConfiggenError::UnrepresentableValues { values, .. } => values,
+        _ => return None,
+    };
+    let values = values
+        .iter()
+        .map(|v| json!({ "path": v.path, "message": v.message }))
+        .collect();
+    Some(Value::Array(values))
+}
+
+const USAGE: u8 = 64;
+const DATA_ERROR: u8 = 65;
+const NO_INPUT: u8 = 66;
+const CANNOT_CREATE: u8 = 73;
+const IO_ERROR: u8 = 74;
+const CONFIG_ERROR: u8 = 78;
+
+/// Returns the name of the variant of `e`, and the exit code matching it
+fn exit_code(e: &ConfiggenError) -> (&'static str, u8) {
+    match e {
+        ConfiggenError::ConfigDirectoryAlreadyExists { .. } => {
+            ("ConfigDirectoryAlreadyExists", CANNOT_CREATE)
+        }
+        ConfiggenError::ConfigFileAlreadyExists { .. } => {
+            ("ConfigFileAlreadyExists", CANNOT_CREATE)
+        }
+        ConfiggenError::ConfigDirectoryCreationFailed { .. } => {
+            ("ConfigDirectoryCreationFailed", CANNOT_CREATE)
+        }
+        ConfiggenError::UnsupportedFormat { .. } => ("UnsupportedFormat", USAGE),
+        ConfiggenError::SerializationFailed { .. } => ("SerializationFailed", DATA_ERROR),
+        ConfiggenError::WritingFailed { .. } => ("WritingFailed", IO_ERROR),
+        ConfiggenError::LoadingFailed { .. } => ("LoadingFailed", DATA_ERROR),
+        ConfiggenError::UnknownFormat { .. } => ("UnknownFormat", USAGE),
+        ConfiggenError::AmbiguousFormat { .. } => ("AmbiguousFormat", USAGE),
+        ConfiggenError::UnknownFormatName(_) => ("UnknownFormatName", USAGE),
+        ConfiggenError::HomeDirectoryNotFound => ("HomeDirectoryNotFound", CONFIG_ERROR),
+        ConfiggenError::FileCreationFailed { .. } => ("FileCreationFailed", CANNOT_CREATE),
+        ConfiggenError::FlushFailed { .. } => ("FlushFailed", IO_ERROR),
+        ConfiggenError::ParsingFailed { .. } => ("ParsingFailed", DATA_ERROR),
+        ConfiggenError::BackupFailed { .. } => ("BackupFailed", IO_ERROR),
+        ConfiggenError::NotATable(_) => ("NotATable", DATA_ERROR),
+        ConfiggenError::UnsupportedConfigVersion { .. } => ("UnsupportedConfigVersion", DATA_ERROR),
+        ConfiggenError::MissingMigration { .. } => ("MissingMigration", DATA_ERROR),
+        ConfiggenError::MigrationFailed { .. } => ("MigrationFailed", DATA_ERROR),
+        ConfiggenError::ValidationFailed { .. } => ("ValidationFailed", DATA_ERROR),
+        ConfiggenError::UnrepresentableValues { .. } => ("UnrepresentableValues", DATA_ERROR),
+        ConfiggenError::InvalidEnvOverride { .. } => ("InvalidEnvOverride", DATA_ERROR),
+        ConfiggenError::WatchFailed { .. } => ("WatchFailed", IO_ERROR),
+        ConfiggenError::ReadingFailed { .. } => ("ReadingFailed", NO_INPUT),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_exit_code() {
+        let e = ConfiggenError::ReadingFailed {
+            path: PathBuf::from("config.toml"),
+            source: std::io::Error::new(std::io::ErrorKind::NotFound, "not found"),
+        };
+        assert_eq!(exit_code(&e), ("ReadingFailed", NO_INPUT));
+        assert_eq!(error_message(&e), "Reading config.toml failed: not found");
+        let e = ConfiggenError::AmbiguousFormat {
+            path: PathBuf::from("app.cfg"),
+            extension: "cfg".to_owned(),
+        };
+        assert_eq!(exit_code(&e), ("AmbiguousFormat", USAGE));
+        assert_eq!(
+            error_message(&e),
+            "Extension `cfg` of app.cfg does not tell which serialization format to use"
+        );
+    }
+}
@@ -0,0 +1,437 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::formats;
+use crate::initialization::{write_config_file, InitializationOptions};
+use crate::utils::read_configuration;
+use crate::value::join_path;
+use crate::Error as ConfiggenError;
+use crate::{InitializationOutcome, OverwritePolicy, SerializationFormat, Violation};
+
+/// Converts the configuration file at `input_path`, written in `from`, into a configuration file
+/// at `output_path` written in `to`
+///
+/// The values keep their types and the keys their order, as far as the target format allows it
+/// (TOML writes the tables after the other keys of a table). Nothing is written if a value cannot
+/// be represented in `to` : see `unrepresentable_values`. A file converted into its own format is
+/// copied as is once parsed, since `to` can then hold everything the file holds.
+///
+/// # Arguments
+/// * `input_path` - The path to the configuration file to convert
+/// * `from` - The format of the file to convert
+/// * `output_path` - The path to the configuration file to write
+/// * `to` - The format of the file to write
+/// * `policy` - What to do if the file at `output_path` already exists
+///
+/// # Returns
+/// * Ok(InitializationOutcome) telling what has been done to the file at `output_path`
+/// * Err(ConfiggenError::ReadingFailed) if the file at `input_path` cannot be read
+/// * Err(ConfiggenError::ParsingFailed) if the file at `input_path` is not valid in `from`
+/// * Err(ConfiggenError::UnrepresentableValues) listing all the values that cannot be represented
+///   in `to`, including the keys that are not strings (YAML and RON) and the TOML datetimes
+/// * The errors returned by `initialization::initialize_config_file` if writing the file fails
+pub fn convert_config_file(
+    input_path: &Path,
+    from: SerializationFormat,
+    output_path: &Path,
+    to: SerializationFormat,
+    policy: OverwritePolicy,
+) -> Result<InitializationOutcome, ConfiggenError> {
+    let content = read_configuration(input_path)?;
+    let parsing_failed = |e| ConfiggenError::ParsingFailed {
+        path: input_path.to_path_buf(),
+        format: from,
+        source: e,
+    };
+
+    let config = formats::parse_value(&content, from).map_err(parsing_failed)?;
+    let data = match from == to {
+        true => content,
+        false => {
+            // The non-string keys and the TOML datetimes cannot make it into the format-neutral
+            // value tree, so they are looked for beforehand to be reported along with the other
+            // unrepresentable values
+            let values = lost_in_value_tree(&content, from).map_err(parsing_failed)?;
+            if !values.is_empty() {
+                return Err(ConfiggenError::UnrepresentableValues { format: to, values });
+            }
+            convert_value(&config, to)?
+        }
+    };
+    write_config_file(
+        &config,
+        &data,
+        output_path,
+        to,
+        policy,
+        &InitializationOptions::default(),
+    )
+}
+
+/// Serializes the format-neutral value tree `config` in `format`, refusing to leave out or alter
+/// the values that `format` cannot represent
+///
+/// # Returns
+/// * Ok(String) containing the serialized configuration
+/// * Err(ConfiggenError::UnrepresentableValues) listing all the values that cannot be represented
+///   in `format`
+/// * Err(ConfiggenError::SerializationFailed) if the serialization fails
+pub fn convert_value(
+    config: &Value,
+    format: SerializationFormat,
+) -> Result<String, ConfiggenError> {
+    let values = unrepresentable_values(config, format);
+    if !values.is_empty() {
+        return Err(ConfiggenError::UnrepresentableValues { format, values });
+    }
+    formats::value_to_string(config, format)
+}
+
+/// Returns the values of `config` that cannot be represented in `format`, such as `null` values in
+/// TOML or sequences in INI
+pub fn unrepresentable_values(config: &Value, format: SerializationFormat) -> Vec<Violation> {
+    let mut values = vec![];
+    match format {
+        SerializationFormat::Toml => match config {
+            Value::Object(_) => check_toml("", config, &mut values),
+            _ => values.push(Violation::new("", "is not a table")),
+        },
+        SerializationFormat::Ini => match config {
+            Value::Object(table) => {
+                for (key, value) in table.iter() {
+                    match value {
+                        Value::Object(section) => {
+                            for (k, v) in section.iter() {
+                                check_ini_scalar(&join_path(key, k), v, &mut values);
+                            }
+                        }
+                        value => check_ini_scalar(key, value, &mut values),
+                    }
+                }
+            }
+            _ => values.push(Violation::new("", "is not a table")),
+        },
+        // The other formats can hold any value tree with string keys
+        _ => (),
+    }
+    values
+}
+
+fn check_toml(path: &str, value: &Value, values: &mut Vec<Violation>) {
+    match value {
+        Value::Null => values.push(Violation::new(path, "is null")),
+        Value::Number(n) if n.is_u64() && !n.is_i64() => values.push(Violation::new(
+            path,
+            "is larger than a 64-bit signed integer",
+        )),
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                check_toml(&format!("{}[{}]", path, i), item, values);
+            }
+        }
+        Value::Object(table) => {
+            for (key, value) in table.iter() {
+                check_toml(&join_path(path, key), value, values);
+            }
+        }
+        _ => (),
+    }
+}
+
+fn check_ini_scalar(path: &str, value: &Value, values: &mut Vec<Violation>) {
+    match value {
+        Value::Null => values.push(Violation::new(path, "is null")),
+        Value::Array(_) => values.push(Violation::new(path, "is a sequence")),
+        Value::Object(_) => values.push(Violation::new(
+            path,
+            "is a table nested deeper than one section",
+        )),
+        _ => (),
+    }
+}
+
+/// Returns the keys and values of `content` that the format-neutral value tree cannot hold : the
+/// keys that are not strings (YAML and RON), and the TOML datetimes, which it holds as strings
+#[cfg_attr(
+    not(any(feature = "yaml", feature = "ron", feature = "toml")),
+    allow(unused_variables, unused_mut)
+)]
+fn lost_in_value_tree(
+    content: &str,
+    format: SerializationFormat,
+) -> Result<Vec<Violation>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut values = vec![];
+    match format {
+        #[cfg(feature = "yaml")]
+        SerializationFormat::Yaml => {
+            non_string_yaml_keys("", &serde_yaml::from_str(content)?, &mut values)
+        }
+        #[cfg(feature = "ron")]
+        SerializationFormat::Ron => non_string_ron_keys("", &ron::from_str(content)?, &mut values),
+        #[cfg(feature = "toml")]
+        SerializationFormat::Toml => toml_datetimes("", &toml::from_str(content)?, &mut values),
+        _ => (),
+    }
+    Ok(values)
+}
+
+#[cfg(feature = "toml")]
+fn toml_datetimes(path: &str, value: &Value, values: &mut Vec<Violation>) {
+    match value {
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                toml_datetimes(&format!("{}[{}]", path, i), item, values);
+            }
+        }
+        Value::Object(table) => match formats::toml_datetime(table) {
+            Some(_) => values.push(Violation::new(path, "is a TOML datetime")),
+            None => {
+                for (key, value) in table.iter() {
+                    toml_datetimes(&join_path(path, key), value, values);
+                }
+            }
+        },
+        _ => (),
+    }
+}
+
+#[cfg(feature = "yaml")]
+fn non_string_yaml_keys(path: &str, value: &serde_yaml::Value, keys: &mut Vec<Violation>) {
+    use serde_yaml::Value as Yaml;
+    match value {
+        Yaml::Sequence(items) => {
+            for (i, item) in items.iter().enumerate() {
+                non_string_yaml_keys(&format!("{}[{}]", path, i), item, keys);
+            }
+        }
+        Yaml::Mapping(mapping) => {
+            for (key, value) in mapping.iter() {
+                match key {
+                    Yaml::String(key) => non_string_yaml_keys(&join_path(path, key), value, keys),
+                    key => {
+                        let key = serde_yaml::to_string(key).unwrap_or_default();
+                        keys.push(non_string_key(path, key.trim_end()));
+                    }
+                }
+            }
+        }
+        Yaml::Tagged(tagged) => non_string_yaml_keys(path, &tagged.value, keys),
+        _ => (),
+    }
+}
+
+#[cfg(feature = "ron")]
+fn non_string_ron_keys(path: &str, value: &ron::Value, keys: &mut Vec<Violation>) {
+    use ron::Value as Ron;
+    match value {
+        Ron::Seq(items) => {
+            for (i, item) in items.iter().enumerate() {
+                non_string_ron_keys(&format!("{}[{}]", path, i), item, keys);
+            }
+        }
+        Ron::Map(map) => {
+            for (key, value) in map.iter() {
+                match key {
+                    Ron::String(key) => non_string_ron_keys(&join_path(path, key), value, keys),
+                    key => {
+                        let key = ron::to_string(key).unwrap_or_default();
+                        keys.push(non_string_key(path, &key));
+                    }
+                }
+            }
+        }
+        Ron::Option(Some(value)) => non_string_ron_keys(path, value, keys),
+        _ => (),
+    }
+}
+
+#[cfg(any(feature = "yaml", feature = "ron"))]
+fn non_string_key(path: &str, key: &str) -> Violation {
+    Violation::new(join_path(path, key), "is a key that is not a string")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use temp_dir::TempDir;
+
+    #[test]
+    pub fn test_convert_config_file() {
+        let tmpdir = TempDir::new().unwrap();
+        let input = tmpdir.child("config.json");
+        let output = tmpdir.child("config.toml");
+        std::fs::write(
+            &input,
+            r#"{"name": "mine", "ratio": 0.5, "workers": 4, "verbose": true, "tags": ["b", "a"], "server": {"port": 8080, "host": "localhost"}}"#,
+        )
+        .unwrap();
+
+        let outcome = convert_config_file(
+            &input,
+            SerializationFormat::Json,
+            &output,
+            SerializationFormat::Toml,
+            OverwritePolicy::Fail,
+        )
+        .unwrap();
+        assert_eq!(outcome, InitializationOutcome::Created);
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            r#"name = "mine"
+ratio = 0.5
+workers = 4
+verbose = true
+tags = ["b", "a"]
+
+[server]
+port = 8080
+host = "localhost"
+"#
+        );
+
+        // And back, without losing any type or order
+        let back = tmpdir.child("back.json");
+        convert_config_file(
+            &output,
+            SerializationFormat::Toml,
+            &back,
+            SerializationFormat::Json,
+            OverwritePolicy::Fail,
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&back).unwrap(),
+            std::fs::read_to_string(&input).unwrap().replace(' ', "")
+        );
+    }
+
+    #[test]
+    pub fn test_convert_toml_datetimes() {
+        let tmpdir = TempDir::new().unwrap();
+        let input = tmpdir.child("config.toml");
+        let output = tmpdir.child("config.json");
+        let content = "when = 1979-05-27T07:32:00Z
+days = [1979-05-27]
+
+[backup]
+at = 07:32:00
+";
+        std::fs::write(&input, content).unwrap();
+
+        // The datetimes would become strings
+        let r = convert_config_file(
+            &input,
+            SerializationFormat::Toml,
+            &output,
+            SerializationFormat::Json,
+            OverwritePolicy::Fail,
+        );
+        match r {
+            Err(ConfiggenError::UnrepresentableValues { format, values }) => {
+                assert_eq!(format, SerializationFormat::Json);
+                assert_eq!(
+                    values,
+                    vec![
+                        Violation::new("when", "is a TOML datetime"),
+                        Violation::new("days[0]", "is a TOML datetime"),
+                        Violation::new("backup.at", "is a TOML datetime"),
+                    ]
+                );
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+        assert!(!output.exists());
+
+        // But TOML holds them
+        let output = tmpdir.child("copy.toml");
+        convert_config_file(
+            &input,
+            SerializationFormat::Toml,
+            &output,
+            SerializationFormat::Toml,
+            OverwritePolicy::Fail,
+        )
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), content);
+    }
+
+    #[test]
+    pub fn test_unrepresentable_values() {
+        let config = json!({
+            "name": null,
+            "big": u64::MAX,
+            "tags": ["a", null],
+            "server": {"hosts": ["a"], "tls": {"cert": "a.pem"}},
+        });
+        assert_eq!(
+            unrepresentable_values(&config, SerializationFormat::Toml),
+            vec![
+                Violation::new("name", "is null"),
+                Violation::new("big", "is larger than a 64-bit signed integer"),
+                Violation::new("tags[1]", "is null"),
+            ]
+        );
+        assert_eq!(
+            unrepresentable_values(&config, SerializationFormat::Ini),
+            vec![
+                Violation::new("name", "is null"),
+                Violation::new("tags", "is a sequence"),
+                Violation::new("server.hosts", "is a sequence"),
+                Violation::new("server.tls", "is a table nested deeper than one section"),
+            ]
+        );
+        assert!(unrepresentable_values(&config, SerializationFormat::Yaml).is_empty());
+
+        let r = convert_value(&config, SerializationFormat::Toml);
+        match r {
+            Err(ConfiggenError::UnrepresentableValues { format, values }) => {
+                assert_eq!(format, SerializationFormat::Toml);
+                assert_eq!(values.len(), 3);
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    pub fn test_non_string_keys() {
+        let tmpdir = TempDir::new().unwrap();
+        let input = tmpdir.child("config.yaml");
+        let output = tmpdir.child("config.json");
+        let content = "ports:\n  80: http\n  443: https\nname: mine\n8080: proxy\n";
+        std::fs::write(&input, content).unwrap();
+
+        let r = convert_config_file(
+            &input,
+            SerializationFormat::Yaml,
+            &output,
+            SerializationFormat::Json,
+            OverwritePolicy::Fail,
+        );
+        match r {
+            Err(ConfiggenError::UnrepresentableValues { values, .. }) => assert_eq!(
+                values,
+                vec![
+                    Violation::new("ports.80", "is a key that is not a string"),
+                    Violation::new("ports.443", "is a key that is not a string"),
+                    Violation::new("8080", "is a key that is not a string"),
+                ]
+            ),
+            r => panic!("Unexpected result {:?}", r),
+        }
+        assert!(!output.exists());
+
+        // YAML holds them
+        let output = tmpdir.child("copy.yaml");
+        convert_config_file(
+            &input,
+            SerializationFormat::Yaml,
+            &output,
+            SerializationFormat::Yaml,
+            OverwritePolicy::Fail,
+        )
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), content);
+    }
+}
@@ -0,0 +1,298 @@
+use std::fmt;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::formats;
+use crate::Error as ConfiggenError;
+use crate::SerializationFormat;
+
+/// A difference between a configuration and the default one, at the key path `path`
+///
+/// The path holds one segment per table crossed, so that keys containing dots stay unambiguous.
+/// Sequences are compared as a whole, and reported as changed if any of their items differs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// The key is missing from the default configuration
+    Added { path: Vec<String>, value: Value },
+    /// The key of the default configuration is missing from the configuration
+    Removed { path: Vec<String>, value: Value },
+    /// The value differs from the default one
+    Changed {
+        path: Vec<String>,
+        old: Value,
+        new: Value,
+    },
+}
+
+impl Change {
+    /// Returns the path of the changed key, one segment per table crossed
+    pub fn path(&self) -> &[String] {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Changed { path, .. } => path,
+        }
+    }
+
+    /// Returns the path of the changed key with its segments joined by dots (e.g. `server.port`)
+    pub fn dotted_path(&self) -> String {
+        self.path().join(".")
+    }
+
+    /// Returns the path of the changed key as a JSON Pointer (RFC 6901, e.g. `/server/port`)
+    pub fn json_pointer(&self) -> String {
+        self.path()
+            .iter()
+            .map(|segment| format!("/{}", segment.replace('~', "~0").replace('/', "~1")))
+            .collect()
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { value, .. } => write!(f, "+ {} = {}", self.dotted_path(), value),
+            Change::Removed { value, .. } => write!(f, "- {} = {}", self.dotted_path(), value),
+            Change::Changed { old, new, .. } => {
+                write!(f, "~ {} = {} -> {}", self.dotted_path(), old, new)
+            }
+        }
+    }
+}
+
+/// The differences between a configuration and the default one
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConfigDiff {
+    changes: Vec<Change>,
+}
+
+impl ConfigDiff {
+    /// Compares the format-neutral value trees `config` and `default`
+    ///
+    /// A key missing from `config` whose default value is `null` is not reported, since both
+    /// deserialize into `None`.
+    pub fn between(default: &Value, config: &Value) -> Self {
+        ConfigDiff::new(default, config, false)
+    }
+
+    /// Same as `between`, the scalars of `config` being compared with the text of the default
+    /// values if `strings_only` is true, for the formats that only hold strings
+    fn new(default: &Value, config: &Value, strings_only: bool) -> Self {
+        let mut changes = vec![];
+        diff_at(&mut vec![], default, config, strings_only, &mut changes);
+        ConfigDiff { changes }
+    }
+
+    /// Returns true if the configuration is the default one
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns the differences, in the order of the keys of the default configuration, the added
+    /// keys coming after the default ones of the same table
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// Returns the JSON Patch (RFC 6902) turning the default configuration into the configuration
+    pub fn to_json_patch(&self) -> Value {
+        let operations = self
+            .changes
+            .iter()
+            .map(|change| match change {
+                Change::Added { value, .. } => {
+                    json!({ "op": "add", "path": change.json_pointer(), "value": value })
+                }
+                Change::Removed { .. } => json!({ "op": "remove", "path": change.json_pointer() }),
+                Change::Changed { new, .. } => {
+                    json!({ "op": "replace", "path": change.json_pointer(), "value": new })
+                }
+            })
+            .collect();
+        Value::Array(operations)
+    }
+}
+
+/// Writes one change per line, prefixed by `+` (added), `-` (removed) or `~` (changed)
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in self.changes.iter() {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the configuration file at `config_file_path` with the default configuration `config`
+///
+/// Since INI files only hold strings, the scalars of an INI file are compared with the text of the
+/// default values (e.g. `"8080"` is not reported as a change from `8080`).
+///
+/// # Arguments
+/// * `config` - The default config
+/// * `config_file_path` - The path to the configuration file to compare with the default config
+/// * `format` - a `SerializationFormat` value to tell which file format is used
+///
+/// # Returns
+/// * Ok(ConfigDiff) containing the differences, from the default config to the file
+/// * Any error returned by `utils::read_configuration` if the file cannot be read
+/// * Err(ConfiggenError::ParsingFailed) if the file is not valid in `format`
+/// * Err(ConfiggenError::SerializationFailed) if `config` cannot be serialized
+pub fn diff_config_file(
+    config: &impl Serialize,
+    config_file_path: &Path,
+    format: SerializationFormat,
+) -> Result<ConfigDiff, ConfiggenError> {
+    let default =
+        serde_json::to_value(config).map_err(|e| ConfiggenError::SerializationFailed {
+            format,
+            source: Box::new(e),
+        })?;
+    let existing = formats::read_config_value(config_file_path, format)?;
+    let strings_only = format == SerializationFormat::Ini;
+    Ok(ConfigDiff::new(&default, &existing, strings_only))
+}
+
+fn diff_at(
+    path: &mut Vec<String>,
+    default: &Value,
+    config: &Value,
+    strings_only: bool,
+    changes: &mut Vec<Change>,
+) {
+    let (default_table, config_table) = match (default, config) {
+        (Value::Object(d), Value::Object(c)) => (d, c),
+        _ => {
+            if !same_value(default, config, strings_only) {
+                changes.push(Change::Changed {
+                    path: path.clone(),
+                    old: default.clone(),
+                    new: config.clone(),
+                });
+            }
+            return;
+        }
+    };
+
+    for (key, default) in default_table.iter() {
+        path.push(key.clone());
+        match config_table.get(key) {
+            Some(config) => diff_at(path, default, config, strings_only, changes),
+            None if default.is_null() => (),
+            None => changes.push(Change::Removed {
+                path: path.clone(),
+                value: default.clone(),
+            }),
+        }
+        path.pop();
+    }
+    let added = config_table
+        .iter()
+        .filter(|(key, _)| !default_table.contains_key(*key));
+    for (key, value) in added {
+        let mut path = path.clone();
+        path.push(key.clone());
+        changes.push(Change::Added {
+            path,
+            value: value.clone(),
+        });
+    }
+}
+
+fn same_value(default: &Value, config: &Value, strings_only: bool) -> bool {
+    match (default, config) {
+        (Value::Bool(_) | Value::Number(_), Value::String(s)) if strings_only => {
+            serde_json::from_str::<Value>(s).is_ok_and(|parsed| parsed == *default)
+        }
+        _ => default == config,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::get_server_config;
+    use temp_dir::TempDir;
+
+    #[test]
+    pub fn test_diff_config_file() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("config.toml");
+        std::fs::write(
+            &path,
+            "tags = [\"a\", \"b\"]\nverbose = true\n\n[server]\nhost = \"localhost\"\nport = 9090\n\"a/b~c\" = 1\n",
+        )
+        .unwrap();
+
+        let diff =
+            diff_config_file(&get_server_config(), &path, SerializationFormat::Toml).unwrap();
+        assert_eq!(
+            diff.to_string(),
+            r#"- name = "app"
+~ server.port = 8080 -> 9090
++ server.a/b~c = 1
++ tags = ["a","b"]
++ verbose = true
+"#
+        );
+        assert_eq!(
+            diff.to_json_patch(),
+            json!([
+                { "op": "remove", "path": "/name" },
+                { "op": "replace", "path": "/server/port", "value": 9090 },
+                { "op": "add", "path": "/server/a~1b~0c", "value": 1 },
+                { "op": "add", "path": "/tags", "value": ["a", "b"] },
+                { "op": "add", "path": "/verbose", "value": true },
+            ])
+        );
+
+        // Applying the patch to the default configuration gives back the file
+        let mut patched = serde_json::to_value(get_server_config()).unwrap();
+        let patch: json_patch::Patch = serde_json::from_value(diff.to_json_patch()).unwrap();
+        json_patch::patch(&mut patched, &patch).unwrap();
+        // Apart from the `None` values, that TOML leaves out
+        patched.as_object_mut().unwrap().remove("timeout");
+        assert_eq!(
+            patched,
+            formats::read_value(&path, SerializationFormat::Toml).unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_diff_toml_datetimes() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("config.toml");
+        std::fs::write(&path, "when = 2000-01-01T00:00:00Z\n").unwrap();
+
+        let default = json!({ "when": "1979-05-27T07:32:00Z" });
+        let diff = diff_config_file(&default, &path, SerializationFormat::Toml).unwrap();
+        assert_eq!(
+            diff.to_string(),
+            "~ when = \"1979-05-27T07:32:00Z\" -> \"2000-01-01T00:00:00Z\"\n"
+        );
+    }
+
+    #[test]
+    pub fn test_diff_ignores_schema_key() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("config.json");
+        std::fs::write(&path, r#"{"$schema": "c.schema.json", "port": 9090}"#).unwrap();
+
+        let default = json!({ "port": 8080 });
+        let diff = diff_config_file(&default, &path, SerializationFormat::Json).unwrap();
+        assert_eq!(diff.to_string(), "~ port = 8080 -> 9090\n");
+    }
+
+    #[test]
+    pub fn test_diff_ini_config_file() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("config.ini");
+        std::fs::write(&path, "name=app\n\n[server]\nhost=localhost\nport=8080\n").unwrap();
+
+        let default = get_server_config();
+        let diff = diff_config_file(&default, &path, SerializationFormat::Ini).unwrap();
+        assert!(diff.is_empty(), "{}", diff);
+    }
+}
@@ -0,0 +1,368 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Serialize;
+use toml_edit::ser::ValueSerializer;
+use toml_edit::{Document, InlineTable, Item, Table, TableLike, TomlError, Value};
+
+use crate::initialization::atomic_write;
+use crate::utils::read_configuration;
+use crate::value::join_path;
+use crate::Error as ConfiggenError;
+use crate::SerializationFormat;
+
+/// A TOML configuration file, edited in place
+///
+/// Unlike a rewrite through `initialize_config_file`, editing a `TomlDocument` keeps the comments,
+/// the whitespace and the key ordering of the existing file : only the edited keys change.
+/// Keys are addressed by their dotted path (e.g. `server.port`), each segment being a bare key.
+#[derive(Debug, Clone, Default)]
+pub struct TomlDocument {
+    document: Document,
+}
+
+impl TomlDocument {
+    /// Reads and parses the TOML file at `path`
+    ///
+    /// # Returns
+    /// * Ok(TomlDocument) if the file could be read and parsed
+    /// * Err(ConfiggenError::ReadingFailed) if the file could not be read
+    /// * Err(ConfiggenError::ParsingFailed) if the content of the file is not valid TOML
+    pub fn open(path: &Path) -> Result<Self, ConfiggenError> {
+        read_configuration(path)?
+            .parse()
+            .map_err(|e| ConfiggenError::ParsingFailed {
+                path: path.to_path_buf(),
+                format: SerializationFormat::Toml,
+                source: Box::new(e),
+            })
+    }
+
+    /// Returns the item at the dotted path `key`, if any
+    pub fn get(&self, key: &str) -> Option<&Item> {
+        let mut item = self.document.as_item();
+        for segment in key.split('.') {
+            item = item.as_table_like()?.get(segment)?;
+        }
+        Some(item)
+    }
+
+    /// Sets the value at the dotted path `key`, creating the missing tables on the way
+    ///
+    /// If the key already exists, its value is replaced but the comments around it are kept.
+    ///
+    /// # Arguments
+    /// * `key` - The dotted path of the key to set
+    /// * `value` - The value to set, which can be anything TOML can represent (scalars, arrays,
+    ///   structs, ...)
+    ///
+    /// # Returns
+    /// * Ok(()) if the value has been set
+    /// * Err(ConfiggenError::SerializationFailed) if `value` cannot be represented in TOML
+    /// * Err(ConfiggenError::NotATable) if a parent of `key` exists but is not a table
+    pub fn set(&mut self, key: &str, value: &impl Serialize) -> Result<(), ConfiggenError> {
+        let value = value
+            .serialize(ValueSerializer::new())
+            .map_err(serialization_failed)?;
+
+        let (parent_path, name) = match key.rsplit_once('.') {
+            Some((parent_path, name)) => (Some(parent_path), name),
+            None => (None, key),
+        };
+        let mut parent: &mut dyn TableLike = self.document.as_table_mut();
+        // Children of an inline table (`{ a = 1 }`) have to be inline as well
+        let mut inline = false;
+        let mut path = String::new();
+        for segment in parent_path.into_iter().flat_map(|p| p.split('.')) {
+            path = join_path(&path, segment);
+            let item = parent.entry(segment).or_insert_with(|| {
+                if inline {
+                    Item::Value(Value::InlineTable(InlineTable::new()))
+                } else {
+                    let mut table = Table::new();
+                    table.set_implicit(true);
+                    Item::Table(table)
+                }
+            });
+            inline |= item.is_value();
+            parent = item
+                .as_table_like_mut()
+                .ok_or_else(|| ConfiggenError::NotATable(path.clone()))?;
+        }
+
+        let new_item = into_item(value, inline);
+        match parent.get_mut(name) {
+            Some(Item::Value(existing)) if new_item.is_value() => {
+                let decor = existing.decor().clone();
+                *existing = new_item.into_value().unwrap_or_else(|_| unreachable!());
+                *existing.decor_mut() = decor;
+            }
+            _ => {
+                parent.insert(name, new_item);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the key at the dotted path `key`, along with its comments
+    ///
+    /// # Returns
+    /// * true if the key existed
+    pub fn remove(&mut self, key: &str) -> bool {
+        let (parent_path, name) = match key.rsplit_once('.') {
+            Some((parent_path, name)) => (Some(parent_path), name),
+            None => (None, key),
+        };
+        let mut parent: &mut dyn TableLike = self.document.as_table_mut();
+        for segment in parent_path.into_iter().flat_map(|p| p.split('.')) {
+            parent = match parent.get_mut(segment).and_then(Item::as_table_like_mut) {
+                Some(table) => table,
+                None => return false,
+            };
+        }
+        parent.remove(name).is_some()
+    }
+
+    /// Inserts the keys of `config` that the document lacks, recursively into nested tables,
+    /// leaving the existing keys and their comments untouched
+    ///
+    /// # Returns
+    /// * Ok(Vec<String>) containing the dotted paths of the added keys (e.g. `server.timeout`)
+    /// * Err(ConfiggenError::SerializationFailed) if `config` cannot be represented in TOML
+    pub fn insert_missing_defaults(
+        &mut self,
+        config: &impl Serialize,
+    ) -> Result<Vec<String>, ConfiggenError> {
+        let defaults = toml_edit::ser::to_document(config).map_err(serialization_failed)?;
+
+        let mut added = vec![];
+        insert_missing(
+            "",
+            self.document.as_table_mut(),
+            false,
+            defaults.as_table(),
+            &mut added,
+        );
+        Ok(added)
+    }
+
+    /// Writes the document to `path`, atomically replacing the file if it already exists
+    ///
+    /// # Returns
+    /// * Ok(()) if the file has been written
+    /// * Err(ConfiggenError::FileCreationFailed) if the temporary file cannot be created
+    /// * Err(ConfiggenError::WritingFailed) if writing the data fails
+    /// * Err(ConfiggenError::FlushFailed) if flushing or syncing the data to disk fails
+    pub fn save(&self, path: &Path) -> Result<(), ConfiggenError> {
+        atomic_write::replace(path, self.to_string().as_bytes(), false)
+    }
+}
+
+impl FromStr for TomlDocument {
+    type Err = TomlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(TomlDocument {
+            document: s.parse()?,
+        })
+    }
+}
+
+impl fmt::Display for TomlDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.document.fmt(f)
+    }
+}
+
+/// Recursive part of `TomlDocument::insert_missing_defaults`, `inline` telling whether `target` is
+/// an inline table
+fn insert_missing(
+    prefix: &str,
+    target: &mut dyn TableLike,
+    inline: bool,
+    defaults: &dyn TableLike,
+    added: &mut Vec<String>,
+) {
+    for (key, default) in defaults.iter() {
+        let path = join_path(prefix, key);
+        match target.get_mut(key) {
+            Some(existing) => {
+                let existing_inline = inline || existing.is_value();
+                if let (Some(existing), Some(default)) =
+                    (existing.as_table_like_mut(), default.as_table_like())
+                {
+                    insert_missing(&path, existing, existing_inline, default, added);
+                }
+            }
+            None => {
+                let item = match default.clone().into_value() {
+                    Ok(value) => into_item(value, inline),
+                    Err(item) => item,
+                };
+                target.insert(key, item);
+                added.push(path);
+            }
+        }
+    }
+}
+
+/// Turns a serialized value into an item to be inserted in a table : tables nested in a standard
+/// table are written as `[section]` blocks rather than inline tables, as `toml::to_string` does
+fn into_item(value: Value, inline_parent: bool) -> Item {
+    match value {
+        Value::InlineTable(table) if !inline_parent => {
+            let mut table = table.into_table();
+            let keys: Vec<String> = table.iter().map(|(k, _)| k.to_owned()).collect();
+            for key in keys {
+                if let Some(item) = table.get_mut(&key) {
+                    let child = std::mem::take(item);
+                    *item = match child.into_value() {
+                        Ok(value) => into_item(value, false),
+                        Err(child) => child,
+                    };
+                }
+            }
+            Item::Table(table)
+        }
+        value => Item::Value(value),
+    }
+}
+
+fn serialization_failed(e: toml_edit::ser::Error) -> ConfiggenError {
+    ConfiggenError::SerializationFailed {
+        format: SerializationFormat::Toml,
+        source: Box::new(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{get_server_config, ServerConfig};
+
+    const ANNOTATED: &str = r#"# Name of the instance
+name = "mine" # set by the operator
+
+# The server the instance listens on
+[server]
+# Keep this one behind the proxy
+host = "example.com"
+"#;
+
+    #[test]
+    pub fn test_set_keeps_comments() {
+        let mut document: TomlDocument = ANNOTATED.parse().unwrap();
+        document.set("name", &"yours").unwrap();
+        document.set("server.port", &8080).unwrap();
+        document.set("client.retries", &3).unwrap();
+
+        let expected = r#"# Name of the instance
+name = "yours" # set by the operator
+
+# The server the instance listens on
+[server]
+# Keep this one behind the proxy
+host = "example.com"
+port = 8080
+
+[client]
+retries = 3
+"#;
+        assert_eq!(document.to_string(), expected);
+        assert_eq!(
+            document.get("server.port").and_then(Item::as_integer),
+            Some(8080)
+        );
+    }
+
+    #[test]
+    pub fn test_set_below_a_value() {
+        let mut document: TomlDocument = ANNOTATED.parse().unwrap();
+        let r = document.set("name.first", &"first");
+        assert!(matches!(r, Err(ConfiggenError::NotATable(ref p)) if p == "name"));
+    }
+
+    #[test]
+    pub fn test_set_below_an_inline_table() {
+        let mut document: TomlDocument = "a = { b = 1 }\n".parse().unwrap();
+        document.set("a.c.d", &5).unwrap();
+        document.set("a.e", &2).unwrap();
+
+        assert_eq!(
+            document.to_string(),
+            "a = { b = 1 , c = { d = 5 }, e = 2 }\n"
+        );
+        let value: toml::Value = toml::from_str(&document.to_string()).unwrap();
+        assert_eq!(value["a"]["c"]["d"].as_integer(), Some(5));
+    }
+
+    #[test]
+    pub fn test_remove() {
+        let mut document: TomlDocument = ANNOTATED.parse().unwrap();
+        assert!(document.remove("server.host"));
+        assert!(!document.remove("server.host"));
+        assert!(!document.remove("client.retries"));
+        assert!(document.get("server.host").is_none());
+        assert!(document.to_string().starts_with("# Name of the instance\n"));
+    }
+
+    #[test]
+    pub fn test_insert_missing_defaults() {
+        let mut document: TomlDocument = ANNOTATED.parse().unwrap();
+        document.remove("name");
+        let added = document
+            .insert_missing_defaults(&get_server_config())
+            .unwrap();
+        assert_eq!(added, vec!["name", "server.port"]);
+
+        let expected = r#"name = "app"
+
+# The server the instance listens on
+[server]
+# Keep this one behind the proxy
+host = "example.com"
+port = 8080
+"#;
+        assert_eq!(document.to_string(), expected);
+        let read_config: ServerConfig = toml::from_str(&document.to_string()).unwrap();
+        assert_eq!(read_config.server.port, 8080);
+
+        let added = document
+            .insert_missing_defaults(&get_server_config())
+            .unwrap();
+        assert!(added.is_empty());
+    }
+
+    #[test]
+    pub fn test_insert_missing_table() {
+        let mut document: TomlDocument = "timeout = 30\n".parse().unwrap();
+        let added = document
+            .insert_missing_defaults(&get_server_config())
+            .unwrap();
+        assert_eq!(added, vec!["name", "server"]);
+        assert_eq!(
+            document.to_string(),
+            "timeout = 30\nname = \"app\"\n\n[server]\nhost = \"localhost\"\nport = 8080\n"
+        );
+    }
+
+    #[test]
+    pub fn test_open_and_save() {
+        let tmpdir = temp_dir::TempDir::new().unwrap();
+        let path = tmpdir.child("config.toml");
+        std::fs::write(&path, ANNOTATED).unwrap();
+
+        let mut document = TomlDocument::open(&path).unwrap();
+        document.set("server.port", &8080).unwrap();
+        document.save(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}port = 8080\n", ANNOTATED)
+        );
+
+        std::fs::write(&path, "name = ").unwrap();
+        let r = TomlDocument::open(&path);
+        assert!(matches!(r, Err(ConfiggenError::ParsingFailed { .. })));
+    }
+}
@@ -0,0 +1,17 @@
+use std::path::PathBuf;
+
+/// What `initialization::initialize_config_file` did to the configuration file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitializationOutcome {
+    /// The file did not exist and has been created
+    Created,
+    /// The file already existed and has been left untouched, either because of
+    /// `OverwritePolicy::Skip` or because `OverwritePolicy::Merge` found no missing key
+    Skipped,
+    /// The file already existed and has been replaced
+    Overwritten,
+    /// The file already existed, has been backed up at the given path, then replaced
+    BackedUp(PathBuf),
+    /// The file already existed and the missing default keys have been added to it
+    Merged,
+}
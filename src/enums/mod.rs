@@ -1,3 +1,7 @@
+pub mod initialization_outcome;
+pub mod overwrite_policy;
 pub mod serialization_format;
 
+pub use initialization_outcome::InitializationOutcome;
+pub use overwrite_policy::OverwritePolicy;
 pub use serialization_format::SerializationFormat;
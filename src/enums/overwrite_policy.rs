@@ -0,0 +1,18 @@
+/// Tells `initialization::initialize_config_file` what to do when the configuration file already
+/// exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OverwritePolicy {
+    /// Return `Error::ConfigFileAlreadyExists`
+    #[default]
+    Fail,
+    /// Leave the existing file untouched
+    Skip,
+    /// Replace the existing file with the default configuration
+    Overwrite,
+    /// Copy the existing file next to it (as `<file>.bak`, or `<file>.bak.<n>` if that one is
+    /// taken), then replace it with the default configuration
+    BackupThenOverwrite,
+    /// Add the keys of the default configuration that are missing from the existing file, leaving
+    /// the values set by the user untouched. The file is not rewritten if no key is missing
+    Merge,
+}
@@ -1,6 +1,176 @@
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+use config::FileFormat;
+
+use crate::Error as ConfiggenError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SerializationFormat {
     Json,
     Json5,
     Toml,
     Ron,
+    Yaml,
+    Ini,
+}
+
+/// Extensions that are commonly used for configuration files, but that do not tell which format
+/// the file is written in
+const AMBIGUOUS_EXTENSIONS: [&str; 3] = ["cfg", "conf", "config"];
+
+impl SerializationFormat {
+    /// Infers the serialization format from the extension of `path`
+    ///
+    /// # Returns
+    /// * Ok(SerializationFormat) if the extension matches one of the handled formats
+    ///   (case-insensitively, `yml` being accepted for YAML)
+    /// * Err(ConfiggenError::AmbiguousFormat) if the extension is a generic one such as `.conf`
+    /// * Err(ConfiggenError::UnknownFormat) if the path has no extension or an unknown one
+    pub fn from_path(path: &Path) -> Result<Self, ConfiggenError> {
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        if AMBIGUOUS_EXTENSIONS.contains(&extension.as_str()) {
+            return Err(ConfiggenError::AmbiguousFormat {
+                path: path.to_path_buf(),
+                extension,
+            });
+        }
+        extension
+            .parse()
+            .map_err(|_| ConfiggenError::UnknownFormat {
+                path: path.to_path_buf(),
+                extension,
+            })
+    }
+
+    /// Returns the usual file extension of the format, without the leading dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SerializationFormat::Json => "json",
+            SerializationFormat::Json5 => "json5",
+            SerializationFormat::Toml => "toml",
+            SerializationFormat::Ron => "ron",
+            SerializationFormat::Yaml => "yaml",
+            SerializationFormat::Ini => "ini",
+        }
+    }
+}
+
+impl FromStr for SerializationFormat {
+    type Err = ConfiggenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(SerializationFormat::Json),
+            "json5" => Ok(SerializationFormat::Json5),
+            "toml" => Ok(SerializationFormat::Toml),
+            "ron" => Ok(SerializationFormat::Ron),
+            "yaml" | "yml" => Ok(SerializationFormat::Yaml),
+            "ini" => Ok(SerializationFormat::Ini),
+            _ => Err(ConfiggenError::UnknownFormatName(s.to_owned())),
+        }
+    }
+}
+
+impl Display for SerializationFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+impl From<SerializationFormat> for FileFormat {
+    fn from(format: SerializationFormat) -> Self {
+        match format {
+            SerializationFormat::Json => FileFormat::Json,
+            SerializationFormat::Json5 => FileFormat::Json5,
+            SerializationFormat::Toml => FileFormat::Toml,
+            SerializationFormat::Ron => FileFormat::Ron,
+            SerializationFormat::Yaml => FileFormat::Yaml,
+            SerializationFormat::Ini => FileFormat::Ini,
+        }
+    }
+}
+
+impl From<FileFormat> for SerializationFormat {
+    fn from(format: FileFormat) -> Self {
+        match format {
+            FileFormat::Json => SerializationFormat::Json,
+            FileFormat::Json5 => SerializationFormat::Json5,
+            FileFormat::Toml => SerializationFormat::Toml,
+            FileFormat::Ron => SerializationFormat::Ron,
+            FileFormat::Yaml => SerializationFormat::Yaml,
+            FileFormat::Ini => SerializationFormat::Ini,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_from_path() {
+        let format = SerializationFormat::from_path(Path::new("/etc/app/settings.toml"));
+        assert_eq!(format, Ok(SerializationFormat::Toml));
+        let format = SerializationFormat::from_path(Path::new("settings.YML"));
+        assert_eq!(format, Ok(SerializationFormat::Yaml));
+        let format = SerializationFormat::from_path(Path::new("settings.json5"));
+        assert_eq!(format, Ok(SerializationFormat::Json5));
+
+        let format = SerializationFormat::from_path(Path::new("/etc/app/settings.conf"));
+        assert!(matches!(
+            format,
+            Err(ConfiggenError::AmbiguousFormat { ref path, ref extension })
+                if path == Path::new("/etc/app/settings.conf") && extension == "conf"
+        ));
+        let format = SerializationFormat::from_path(Path::new("settings.xml"));
+        assert!(matches!(
+            format,
+            Err(ConfiggenError::UnknownFormat { ref path, ref extension })
+                if path == Path::new("settings.xml") && extension == "xml"
+        ));
+        let format = SerializationFormat::from_path(Path::new("settings"));
+        assert!(matches!(
+            format,
+            Err(ConfiggenError::UnknownFormat { ref extension, .. }) if extension.is_empty()
+        ));
+        assert_eq!(
+            format.unwrap_err().to_string(),
+            "The extension of settings does not match any serialization format"
+        );
+
+        let format = "xml".parse::<SerializationFormat>();
+        assert!(matches!(format, Err(ConfiggenError::UnknownFormatName(ref e)) if e == "xml"));
+    }
+
+    #[test]
+    pub fn test_display_from_str_round_trip() {
+        for format in [
+            SerializationFormat::Json,
+            SerializationFormat::Json5,
+            SerializationFormat::Toml,
+            SerializationFormat::Ron,
+            SerializationFormat::Yaml,
+            SerializationFormat::Ini,
+        ] {
+            assert_eq!(format.to_string().parse(), Ok(format));
+        }
+    }
+
+    #[test]
+    pub fn test_file_format_conversion() {
+        assert_eq!(
+            FileFormat::from(SerializationFormat::Json5),
+            FileFormat::Json5
+        );
+        assert_eq!(
+            SerializationFormat::from(FileFormat::Yaml),
+            SerializationFormat::Yaml
+        );
+    }
 }
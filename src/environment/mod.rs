@@ -0,0 +1,335 @@
+use std::path::Path;
+
+use config::{Config, File, FileFormat};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Number, Value};
+
+use crate::formats;
+use crate::migration;
+use crate::Error as ConfiggenError;
+use crate::SerializationFormat;
+
+/// Overrides of the configuration through environment variables, such as `MYAPP__SERVER__PORT`
+/// overriding the `port` key of the `server` table
+///
+/// The segments of the variable names are matched case-insensitively against the keys of the
+/// configuration file, and the keys that do not exist yet are written in lowercase. The values are
+/// converted to the type of the value they override : booleans, numbers, strings, and JSON for
+/// sequences and tables. Values overriding nothing are parsed as JSON scalars if possible, and kept
+/// as strings otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvOverrides {
+    prefix: String,
+    separator: String,
+}
+
+impl EnvOverrides {
+    /// # Arguments
+    /// * `prefix` - The prefix of the variables to take into account (e.g. `MYAPP`), separated
+    ///   from the keys by the separator (`__` by default)
+    pub fn new(prefix: &str) -> Self {
+        EnvOverrides {
+            prefix: prefix.to_owned(),
+            separator: "__".to_owned(),
+        }
+    }
+
+    /// Sets the separator between the prefix and the segments of the key path
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_owned();
+        self
+    }
+
+    /// Overrides the values of `config` with the variables of `vars` starting with the prefix
+    ///
+    /// # Returns
+    /// * Ok(Vec<String>) containing the dotted paths of the overridden keys, ordered by variable
+    ///   name
+    /// * Err(ConfiggenError::InvalidEnvOverride) if a variable cannot be converted to the type of
+    ///   the value it overrides, or goes through a value that is not a table
+    pub fn apply(
+        &self,
+        config: &mut Value,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Vec<String>, ConfiggenError> {
+        let start = format!("{}{}", self.prefix, self.separator);
+        let mut vars: Vec<(String, String)> = vars
+            .into_iter()
+            .filter(|(name, _)| name.len() > start.len() && name.starts_with(&start))
+            .collect();
+        vars.sort();
+
+        let mut overridden = vec![];
+        for (name, raw) in vars {
+            let invalid = |message: String| ConfiggenError::InvalidEnvOverride {
+                variable: name.clone(),
+                source: message.into(),
+            };
+            let segments: Vec<&str> = name[start.len()..].split(&self.separator).collect();
+            if segments.iter().any(|s| s.is_empty()) {
+                return Err(invalid("The key path has an empty segment".to_owned()));
+            }
+
+            let mut table = match config {
+                Value::Object(table) => table,
+                _ => return Err(invalid("The configuration is not a table".to_owned())),
+            };
+            let mut path = String::new();
+            let (last, parents) = segments.split_last().expect("segments are not empty");
+            for segment in parents {
+                let key = matching_key(table, segment);
+                path = crate::value::join_path(&path, &key);
+                let child = table
+                    .entry(key)
+                    .or_insert_with(|| Value::Object(Map::new()));
+                table = match child {
+                    Value::Object(child) => child,
+                    _ => return Err(invalid(format!("`{}` is not a table", path))),
+                };
+            }
+
+            let key = matching_key(table, last);
+            path = crate::value::join_path(&path, &key);
+            let value = coerce(&raw, table.get(&key)).map_err(invalid)?;
+            table.insert(key, value);
+            overridden.push(path);
+        }
+        Ok(overridden)
+    }
+}
+
+/// Loads the configuration file at `config_file_path`, overridden by the environment variables
+/// of the process (see `EnvOverrides`)
+///
+/// As with `initialization::load_config`, an older file is migrated first if migrations are
+/// registered for `T`.
+///
+/// # Returns
+/// * Ok((T, Vec<String>)) containing the configuration and the dotted paths of the keys overridden
+///   by the environment
+/// * Any error returned by `utils::read_configuration` if the file cannot be read
+/// * Err(ConfiggenError::ParsingFailed) if the file is not valid in `format`
+/// * Any error returned by `migration::migrate_config_file` if migrations are registered for `T`
+/// * Err(ConfiggenError::InvalidEnvOverride) if a variable cannot override the configuration
+/// * Err(ConfiggenError::LoadingFailed) if the overridden configuration cannot be deserialized
+pub fn load_config_with_env<T: DeserializeOwned + 'static>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+    overrides: &EnvOverrides,
+) -> Result<(T, Vec<String>), ConfiggenError> {
+    let vars = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+    load_config_with_vars(config_file_path, format, overrides, vars)
+}
+
+/// Same as `load_config_with_env`, with the variables taken from `vars` instead of the
+/// environment of the process
+pub fn load_config_with_vars<T: DeserializeOwned + 'static>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+    overrides: &EnvOverrides,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<(T, Vec<String>), ConfiggenError> {
+    migration::migrate_registered::<T>(config_file_path, format)?;
+    let mut config = formats::read_config_value(config_file_path, format)?;
+    let overridden = overrides.apply(&mut config, vars)?;
+
+    // Going through the config crate keeps the conversions of `load_config`, such as the INI
+    // strings read as numbers
+    let loading_failed = |e| ConfiggenError::LoadingFailed {
+        path: config_file_path.to_path_buf(),
+        source: e,
+    };
+    let source = File::from_str(&config.to_string(), FileFormat::Json);
+    let config = Config::builder()
+        .add_source(source)
+        .build()
+        .map_err(loading_failed)?;
+    let config = config.try_deserialize::<T>().map_err(loading_failed)?;
+    Ok((config, overridden))
+}
+
+/// Returns the key of `table` matching `segment` case-insensitively, or `segment` in lowercase
+fn matching_key(table: &Map<String, Value>, segment: &str) -> String {
+    table
+        .keys()
+        .find(|key| key.eq_ignore_ascii_case(segment))
+        .cloned()
+        .unwrap_or_else(|| segment.to_lowercase())
+}
+
+/// Converts `raw` to the type of `existing`, the value it overrides
+fn coerce(raw: &str, existing: Option<&Value>) -> Result<Value, String> {
+    match existing {
+        Some(Value::String(_)) => Ok(Value::String(raw.to_owned())),
+        Some(Value::Bool(_)) => match raw.to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => Ok(Value::Bool(true)),
+            "false" | "0" | "no" | "off" => Ok(Value::Bool(false)),
+            _ => Err(format!("`{}` is not a boolean", raw)),
+        },
+        Some(Value::Number(_)) => parse_number(raw)
+            .map(Value::Number)
+            .ok_or_else(|| format!("`{}` is not a number", raw)),
+        Some(existing @ Value::Array(_)) | Some(existing @ Value::Object(_)) => {
+            match serde_json::from_str::<Value>(raw) {
+                Ok(value)
+                    if value.is_array() == existing.is_array()
+                        && value.is_object() == existing.is_object() =>
+                {
+                    Ok(value)
+                }
+                _ => Err(format!(
+                    "`{}` is not a JSON {}",
+                    raw,
+                    match existing.is_array() {
+                        true => "array",
+                        false => "object",
+                    }
+                )),
+            }
+        }
+        Some(Value::Null) | None => match serde_json::from_str::<Value>(raw) {
+            Ok(value @ (Value::Bool(_) | Value::Number(_))) => Ok(value),
+            _ => Ok(Value::String(raw.to_owned())),
+        },
+    }
+}
+
+fn parse_number(raw: &str) -> Option<Number> {
+    if let Ok(n) = raw.parse::<i64>() {
+        return Some(n.into());
+    }
+    if let Ok(n) = raw.parse::<u64>() {
+        return Some(n.into());
+    }
+    raw.parse::<f64>().ok().and_then(Number::from_f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::Server;
+    use serde::Deserialize;
+    use temp_dir::TempDir;
+
+    /// A value of every type that the variables are converted to
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct TypedConfig {
+        pub name: String,
+        pub ratio: f64,
+        pub verbose: bool,
+        pub tags: Vec<String>,
+        pub server: Server,
+        pub timeout: Option<u32>,
+    }
+
+    const CONFIG: &str = r#"name = "mine"
+ratio = 0.5
+verbose = false
+tags = ["a"]
+
+[server]
+host = "localhost"
+port = 8080
+"#;
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    pub fn test_load_config_with_vars() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("config.toml");
+        std::fs::write(&path, CONFIG).unwrap();
+
+        let (config, overridden) = load_config_with_vars::<TypedConfig>(
+            &path,
+            SerializationFormat::Toml,
+            &EnvOverrides::new("MYAPP"),
+            vars(&[
+                ("MYAPP__SERVER__PORT", "9090"),
+                ("MYAPP__VERBOSE", "true"),
+                ("MYAPP__NAME", "1234"),
+                ("MYAPP__TAGS", r#"["b", "c"]"#),
+                ("MYAPP__TIMEOUT", "30"),
+                ("OTHERAPP__NAME", "other"),
+                ("MYAPP", "nothing"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(config.name, "1234");
+        assert_eq!(config.ratio, 0.5);
+        assert_eq!(config.tags, vec!["b", "c"]);
+        assert_eq!(config.server.port, 9090);
+        assert!(config.verbose);
+        assert_eq!(config.timeout, Some(30));
+        assert_eq!(
+            overridden,
+            vec!["name", "server.port", "tags", "timeout", "verbose"]
+        );
+    }
+
+    #[test]
+    pub fn test_load_config_with_vars_ignores_schema_key() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        #[serde(deny_unknown_fields)]
+        struct StrictConfig {
+            pub port: u16,
+        }
+
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("config.json");
+        std::fs::write(&path, r#"{"$schema": "config.schema.json", "port": 8080}"#).unwrap();
+
+        let (config, overridden) = load_config_with_vars::<StrictConfig>(
+            &path,
+            SerializationFormat::Json,
+            &EnvOverrides::new("MYAPP"),
+            vars(&[("MYAPP__PORT", "9090")]),
+        )
+        .unwrap();
+        assert_eq!(config, StrictConfig { port: 9090 });
+        assert_eq!(overridden, vec!["port"]);
+    }
+
+    #[test]
+    pub fn test_separator() {
+        let mut config: Value = serde_json::json!({"server": {"maxConnections": 10}});
+        let overridden = EnvOverrides::new("APP")
+            .separator("_")
+            .apply(
+                &mut config,
+                vars(&[("APP_SERVER_MAXCONNECTIONS", "20"), ("APP_NEW", "x")]),
+            )
+            .unwrap();
+        assert_eq!(overridden, vec!["new", "server.maxConnections"]);
+        assert_eq!(
+            config,
+            serde_json::json!({"server": {"maxConnections": 20}, "new": "x"})
+        );
+    }
+
+    #[test]
+    pub fn test_invalid_overrides() {
+        let config = serde_json::json!({"port": 8080, "tls": false, "name": "mine"});
+        let overrides = EnvOverrides::new("APP");
+        for (name, value) in [
+            ("APP__PORT", "high"),
+            ("APP__TLS", "maybe"),
+            ("APP__NAME__FIRST", "first"),
+            ("APP__PORT____X", "1"),
+        ] {
+            let r = overrides.apply(&mut config.clone(), vars(&[(name, value)]));
+            match r {
+                Err(ConfiggenError::InvalidEnvOverride { variable, .. }) => {
+                    assert_eq!(variable, name)
+                }
+                r => panic!("Unexpected result {:?} for {}", r, name),
+            }
+        }
+    }
+}
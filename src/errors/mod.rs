@@ -1,38 +1,156 @@
+use std::path::PathBuf;
+
 use thiserror::Error;
 
+use crate::traits::Violation;
+use crate::SerializationFormat;
+
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("Configuration directory already exists")]
-    ConfigDirectoryAlreadyExists(#[source] std::io::Error),
-    #[error("Configuration file already exists")]
-    ConfigFileAlreadyExists(#[source] std::io::Error),
-    #[error("Configuration directory creation failed")]
-    ConfigDirectoryCreationFailed(#[source] std::io::Error),
-    #[error("Unhandled serialization format")]
-    UnsupportedFormat(#[source] std::io::Error),
-    #[error("Unhandled serialization format")]
-    SerializationFailed(#[source] Box<dyn std::error::Error>),
-    #[error("Writing failed")]
-    WritingFailed(#[source] std::io::Error),
+    #[error("Configuration directory {} already exists", path.display())]
+    ConfigDirectoryAlreadyExists {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Configuration file {} already exists", path.display())]
+    ConfigFileAlreadyExists {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Creation of the configuration directory {} failed", path.display())]
+    ConfigDirectoryCreationFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Unhandled serialization format {format}")]
+    UnsupportedFormat {
+        format: SerializationFormat,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Serialization of the configuration to {format} failed")]
+    SerializationFailed {
+        format: SerializationFormat,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("Writing to {} failed", path.display())]
+    WritingFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Loading the configuration from {} failed", path.display())]
+    LoadingFailed {
+        path: PathBuf,
+        #[source]
+        source: config::ConfigError,
+    },
+    #[error("The extension of {} does not match any serialization format", path.display())]
+    UnknownFormat { path: PathBuf, extension: String },
+    #[error("Extension `{extension}` of {} does not tell which serialization format to use", path.display())]
+    AmbiguousFormat { path: PathBuf, extension: String },
+    #[error("Unknown serialization format `{0}`")]
+    UnknownFormatName(String),
+    #[error("Could not find the home directory of the current user")]
+    HomeDirectoryNotFound,
+    #[error("Creation of the file {} failed", path.display())]
+    FileCreationFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Flushing {} to disk failed", path.display())]
+    FlushFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Parsing {} as {format} failed", path.display())]
+    ParsingFailed {
+        path: PathBuf,
+        format: SerializationFormat,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("Backing up {} failed", path.display())]
+    BackupFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("`{0}` is not a table, so no key can be set below it")]
+    NotATable(String),
+    #[error("Version {version} of {} is newer than the latest supported version {latest_version}", path.display())]
+    UnsupportedConfigVersion {
+        path: PathBuf,
+        version: u64,
+        latest_version: u64,
+    },
+    #[error("No migration of {} from version {from_version} is registered", path.display())]
+    MissingMigration { path: PathBuf, from_version: u64 },
+    #[error("Migrating {} from version {from_version} failed", path.display())]
+    MigrationFailed {
+        path: PathBuf,
+        from_version: u64,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("Validation of {} failed : {}", path.display(), join_violations(violations))]
+    ValidationFailed {
+        path: PathBuf,
+        violations: Vec<Violation>,
+    },
+    #[error(
+        "Values of the configuration cannot be represented in {format} : {}",
+        join_violations(values)
+    )]
+    UnrepresentableValues {
+        format: SerializationFormat,
+        values: Vec<Violation>,
+    },
+    #[error("Environment variable `{variable}` cannot override the configuration")]
+    InvalidEnvOverride {
+        variable: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("Watching {} for changes failed", path.display())]
+    WatchFailed {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("Reading {} failed", path.display())]
+    ReadingFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
 }
 
+fn join_violations(violations: &[Violation]) -> String {
+    violations
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// `Error` has to stay usable across threads and async tasks, as well as convertible into
+// `anyhow::Error` and the like : this fails to compile if a non thread-safe source sneaks in
+const _: () = assert_send_sync::<Error>();
+
+const fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+/// Errors are compared by variant only, so that tests can check which kind of error is returned
+/// without having to build the exact same paths and sources
 impl PartialEq for Error {
     fn eq(&self, other: &Self) -> bool {
-        matches!(
-            (self, other),
-            (
-                Self::ConfigDirectoryAlreadyExists(_),
-                Self::ConfigDirectoryAlreadyExists(_)
-            ) | (
-                Self::ConfigFileAlreadyExists(_),
-                Self::ConfigFileAlreadyExists(_)
-            ) | (
-                Self::ConfigDirectoryCreationFailed(_),
-                Self::ConfigDirectoryCreationFailed(_)
-            ) | (Self::UnsupportedFormat(_), Self::UnsupportedFormat(_))
-                | (Self::SerializationFailed(_), Self::SerializationFailed(_))
-                | (Self::WritingFailed(_), Self::WritingFailed(_))
-        )
+        std::mem::discriminant(self) == std::mem::discriminant(other)
     }
 }
 
@@ -44,20 +162,68 @@ mod tests {
 
     #[test]
     pub fn test_error_comparison() {
-        let err1 = Error::ConfigFileAlreadyExists(std::io::Error::new(
-            std::io::ErrorKind::AlreadyExists,
-            "",
-        ));
-        let err2 = Error::ConfigFileAlreadyExists(std::io::Error::new(
-            std::io::ErrorKind::AlreadyExists,
-            "",
-        ));
-        let err3 = Error::ConfigDirectoryCreationFailed(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "",
-        ));
+        let err1 = Error::ConfigFileAlreadyExists {
+            path: PathBuf::from("/tmp/config.toml"),
+            source: std::io::Error::new(std::io::ErrorKind::AlreadyExists, ""),
+        };
+        let err2 = Error::ConfigFileAlreadyExists {
+            path: PathBuf::from("/tmp/other.toml"),
+            source: std::io::Error::new(std::io::ErrorKind::AlreadyExists, ""),
+        };
+        let err3 = Error::ConfigDirectoryCreationFailed {
+            path: PathBuf::from("/tmp"),
+            source: std::io::Error::new(std::io::ErrorKind::Other, ""),
+        };
 
         assert_eq!(err1, err2);
         assert_ne!(err1, err3);
     }
+
+    #[test]
+    pub fn test_error_display() {
+        let err = Error::WritingFailed {
+            path: PathBuf::from("/etc/app/config.toml"),
+            source: std::io::Error::new(std::io::ErrorKind::Other, "disk full"),
+        };
+        assert_eq!(err.to_string(), "Writing to /etc/app/config.toml failed");
+
+        let err = Error::SerializationFailed {
+            format: SerializationFormat::Toml,
+            source: Box::new(std::io::Error::new(std::io::ErrorKind::Other, "")),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Serialization of the configuration to toml failed"
+        );
+    }
+
+    #[test]
+    pub fn test_validation_error_display() {
+        let err = Error::ValidationFailed {
+            path: PathBuf::from("/etc/app/config.toml"),
+            violations: vec![
+                Violation::new("server.port", "must be above 1024"),
+                Violation::new("name", "must not be empty"),
+            ],
+        };
+        assert_eq!(
+            err.to_string(),
+            "Validation of /etc/app/config.toml failed : `server.port` must be above 1024, `name` must not be empty"
+        );
+    }
+
+    #[test]
+    pub fn test_error_is_thread_safe() {
+        let handle = std::thread::spawn(|| Error::SerializationFailed {
+            format: SerializationFormat::Json,
+            source: Box::new(std::io::Error::new(std::io::ErrorKind::Other, "")),
+        });
+        let err = handle.join().unwrap();
+
+        let boxed: Box<dyn std::error::Error + Send + Sync + 'static> = Box::new(err);
+        assert_eq!(
+            boxed.to_string(),
+            "Serialization of the configuration to json failed"
+        );
+    }
 }
@@ -0,0 +1,44 @@
+//! Configuration types shared by the tests of the crate
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Validate, Violation};
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Server {
+    pub host: String,
+    pub port: u16,
+}
+
+/// A configuration with a nested table and an optional value, that every format (INI included)
+/// can hold
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ServerConfig {
+    pub name: String,
+    pub timeout: Option<u32>,
+    pub server: Server,
+}
+
+impl Validate for ServerConfig {
+    fn validate(&self) -> Vec<Violation> {
+        let mut violations = vec![];
+        if self.name.is_empty() {
+            violations.push(Violation::new("name", "must not be empty"));
+        }
+        if self.server.port < 1024 {
+            violations.push(Violation::new("server.port", "must be above 1024"));
+        }
+        violations
+    }
+}
+
+pub fn get_server_config() -> ServerConfig {
+    ServerConfig {
+        name: "app".to_owned(),
+        timeout: None,
+        server: Server {
+            host: "localhost".to_owned(),
+            port: 8080,
+        },
+    }
+}
@@ -0,0 +1,263 @@
+#[cfg(any(feature = "toml-edit", feature = "json5", feature = "ron"))]
+use std::collections::HashMap;
+#[cfg(any(feature = "toml-edit", feature = "json5", feature = "ron"))]
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::Error as ConfiggenError;
+use crate::SerializationFormat;
+
+/// Serializes `config` into a string in the given `format`, the documentation of the fields in
+/// `docs` (keyed by dotted path) being written as comments above them
+///
+/// Comments are written for TOML (`#`, `toml-edit` feature), JSON5 and RON (`//`), which are
+/// pretty-printed so that each key gets its own line. The other formats cannot hold comments, or
+/// are written without them.
+#[cfg_attr(
+    not(any(feature = "toml-edit", feature = "json5", feature = "ron")),
+    allow(unused_variables)
+)]
+pub fn to_documented_string(
+    config: &impl Serialize,
+    format: SerializationFormat,
+    docs: &[(String, String)],
+) -> Result<String, ConfiggenError> {
+    #[cfg(any(feature = "toml-edit", feature = "json5", feature = "ron"))]
+    let docs: HashMap<&str, &str> = docs
+        .iter()
+        .map(|(path, doc)| (path.as_str(), doc.as_str()))
+        .collect();
+    #[cfg(any(feature = "toml-edit", feature = "json5", feature = "ron"))]
+    let serialization_failed = |e: Box<dyn std::error::Error + Send + Sync>| {
+        ConfiggenError::SerializationFailed { format, source: e }
+    };
+
+    match format {
+        #[cfg(feature = "toml-edit")]
+        SerializationFormat::Toml => {
+            let data = super::to_string(config, format)?;
+            let mut document: toml_edit::Document = data
+                .parse()
+                .map_err(|e: toml_edit::TomlError| serialization_failed(Box::new(e)))?;
+            add_toml_comments(document.as_table_mut(), "", &docs);
+            Ok(document.to_string())
+        }
+        #[cfg(feature = "json5")]
+        SerializationFormat::Json5 => {
+            let data = serde_json::to_string_pretty(config)
+                .map_err(|e| serialization_failed(Box::new(e)))?;
+            Ok(with_line_comments(&data, 2, &docs, json_key))
+        }
+        #[cfg(feature = "ron")]
+        SerializationFormat::Ron => {
+            let data = ::ron::ser::to_string_pretty(config, ::ron::ser::PrettyConfig::default())
+                .map_err(|e| serialization_failed(Box::new(e)))?;
+            Ok(with_line_comments(&data, 4, &docs, ron_key))
+        }
+        _ => super::to_string(config, format),
+    }
+}
+
+/// Writes `docs` as the comments of the keys and sections of `table`, whose path is `prefix`
+#[cfg(feature = "toml-edit")]
+fn add_toml_comments(table: &mut toml_edit::Table, prefix: &str, docs: &HashMap<&str, &str>) {
+    let keys: Vec<String> = table.iter().map(|(k, _)| k.to_owned()).collect();
+    for key in keys {
+        let path = crate::value::join_path(prefix, &key);
+        let comment = docs
+            .get(path.as_str())
+            .map(|doc| comment_lines(doc, "", "#"));
+        match table.get_mut(&key) {
+            Some(toml_edit::Item::Table(section)) => {
+                if let Some(comment) = comment {
+                    let decor = section.decor_mut();
+                    let previous = decor.prefix().and_then(|p| p.as_str()).unwrap_or("");
+                    let prefix = format!("{}{}", previous, comment);
+                    decor.set_prefix(prefix);
+                }
+                add_toml_comments(section, &path, docs);
+            }
+            _ => {
+                if let (Some(comment), Some(decor)) = (comment, table.key_decor_mut(&key)) {
+                    let previous = decor.prefix().and_then(|p| p.as_str()).unwrap_or("");
+                    let prefix = format!("{}{}", previous, comment);
+                    decor.set_prefix(prefix);
+                }
+            }
+        }
+    }
+}
+
+/// Inserts `docs` as `//` comments above the keys of a pretty-printed document, in which each
+/// nesting level is indented by `indent` spaces and `key_of` extracts the key starting a line
+#[cfg(any(feature = "json5", feature = "ron"))]
+fn with_line_comments(
+    data: &str,
+    indent: usize,
+    docs: &HashMap<&str, &str>,
+    key_of: fn(&str) -> Option<String>,
+) -> String {
+    let mut output = String::with_capacity(data.len());
+    // The keys leading to the current line, `None` standing for the elements of a sequence
+    let mut keys: Vec<Option<String>> = vec![];
+    for line in data.lines() {
+        let content = line.trim_start();
+        let indentation = &line[..line.len() - content.len()];
+        let depth = indentation.len() / indent;
+        if depth > 0 {
+            if let Some(key) = key_of(content) {
+                keys.truncate(depth - 1);
+                keys.push(Some(key));
+                let path: Option<Vec<&str>> = keys.iter().map(|k| k.as_deref()).collect();
+                if let Some(doc) = path.and_then(|path| docs.get(path.join(".").as_str())) {
+                    output.push_str(&comment_lines(doc, indentation, "//"));
+                }
+            } else if content.ends_with(['{', '[', '(']) {
+                keys.truncate(depth - 1);
+                keys.push(None);
+            }
+        }
+        output.push_str(line);
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(any(feature = "toml-edit", feature = "json5", feature = "ron"))]
+fn comment_lines(doc: &str, indentation: &str, marker: &str) -> String {
+    let mut comment = String::new();
+    for line in doc.lines() {
+        let _ = match line.is_empty() {
+            true => writeln!(comment, "{}{}", indentation, marker),
+            false => writeln!(comment, "{}{} {}", indentation, marker, line),
+        };
+    }
+    comment
+}
+
+/// Returns the key starting `line` if it is a quoted key followed by a colon
+#[cfg(any(feature = "json5", feature = "ron"))]
+fn json_key(line: &str) -> Option<String> {
+    let rest = line.strip_prefix('"')?;
+    let mut escaped = false;
+    let end = rest.char_indices().find_map(|(i, c)| match c {
+        '\\' if !escaped => {
+            escaped = true;
+            None
+        }
+        '"' if !escaped => Some(i),
+        _ => {
+            escaped = false;
+            None
+        }
+    })?;
+    if !rest[end + 1..].starts_with(':') {
+        return None;
+    }
+    serde_json::from_str(&line[..end + 2]).ok()
+}
+
+/// Returns the key starting `line` if it is a struct field or a quoted map key, followed by a colon
+#[cfg(feature = "ron")]
+fn ron_key(line: &str) -> Option<String> {
+    if line.starts_with('"') {
+        return json_key(line);
+    }
+    let end = line.find(|c: char| c != '_' && !c.is_ascii_alphanumeric())?;
+    let key = &line[..end];
+    if key.is_empty() || key.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    line[end..].starts_with(':').then(|| key.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{get_server_config, ServerConfig};
+
+    fn get_config_and_docs() -> (ServerConfig, Vec<(String, String)>) {
+        let mut config = get_server_config();
+        config.timeout = Some(30);
+        let docs = vec![
+            ("name".to_owned(), "Name of the instance".to_owned()),
+            ("server".to_owned(), "The server to listen on".to_owned()),
+            (
+                "server.port".to_owned(),
+                "Port to listen on\n\nBelow 1024, root is required".to_owned(),
+            ),
+        ];
+        (config, docs)
+    }
+
+    #[cfg(feature = "toml-edit")]
+    #[test]
+    pub fn test_toml_comments() {
+        let (config, docs) = get_config_and_docs();
+        let data = to_documented_string(&config, SerializationFormat::Toml, &docs).unwrap();
+        let expected = r#"# Name of the instance
+name = "app"
+timeout = 30
+
+# The server to listen on
+[server]
+host = "localhost"
+# Port to listen on
+#
+# Below 1024, root is required
+port = 8080
+"#;
+        assert_eq!(data, expected);
+        assert_eq!(toml::from_str::<ServerConfig>(&data).unwrap(), config);
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    pub fn test_json5_comments() {
+        let (config, docs) = get_config_and_docs();
+        let data = to_documented_string(&config, SerializationFormat::Json5, &docs).unwrap();
+        let expected = r#"{
+  // Name of the instance
+  "name": "app",
+  "timeout": 30,
+  // The server to listen on
+  "server": {
+    "host": "localhost",
+    // Port to listen on
+    //
+    // Below 1024, root is required
+    "port": 8080
+  }
+}
+"#;
+        assert_eq!(data, expected);
+        assert_eq!(json5_rs::from_str::<ServerConfig>(&data).unwrap(), config);
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    pub fn test_ron_comments() {
+        let (config, docs) = get_config_and_docs();
+        let data = to_documented_string(&config, SerializationFormat::Ron, &docs).unwrap();
+        assert!(data.starts_with("(\n    // Name of the instance\n    name: \"app\",\n"));
+        assert!(data.contains("\n        // Below 1024, root is required\n        port: 8080,\n"));
+        assert_eq!(::ron::from_str::<ServerConfig>(&data).unwrap(), config);
+    }
+
+    #[test]
+    pub fn test_json_without_comments() {
+        let (config, docs) = get_config_and_docs();
+        let data = to_documented_string(&config, SerializationFormat::Json, &docs).unwrap();
+        assert_eq!(data, serde_json::to_string(&config).unwrap());
+    }
+
+    #[test]
+    pub fn test_json_key() {
+        assert_eq!(json_key(r#""port": 8080,"#), Some("port".to_owned()));
+        assert_eq!(json_key(r#""a\"b": 1"#), Some("a\"b".to_owned()));
+        assert_eq!(json_key(r#""port","#), None);
+        assert_eq!(ron_key("port: 8080,"), Some("port".to_owned()));
+        assert_eq!(ron_key("Some(3),"), None);
+    }
+}
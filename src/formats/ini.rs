@@ -0,0 +1,160 @@
+use ini_rs::{EscapePolicy, Ini};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::conversion::unrepresentable_values;
+use crate::formats::without_null_entries;
+use crate::Error as ConfiggenError;
+use crate::SerializationFormat;
+
+/// Serializes `config` into an INI document
+///
+/// Top-level scalars go into the general (unnamed) section, and each nested struct becomes a
+/// `[section]` block holding its own scalars. `None` values are left out, since INI has no way
+/// to express them.
+///
+/// # Returns
+/// * Ok(String) containing the INI document
+/// * Err(ConfiggenError::UnrepresentableValues) listing all the values that cannot be expressed in
+///   INI (sequences, or structs nested deeper than one section), as `conversion::convert_value`
+/// * Err(ConfiggenError::SerializationFailed) if `config` could not be serialized at all
+pub fn to_string(config: &impl Serialize) -> Result<String, ConfiggenError> {
+    let value = serde_json::to_value(config).map_err(serialization_failed)?;
+    let value = without_null_entries(&value);
+    let values = unrepresentable_values(&value, SerializationFormat::Ini);
+    if !values.is_empty() {
+        return Err(ConfiggenError::UnrepresentableValues {
+            format: SerializationFormat::Ini,
+            values,
+        });
+    }
+    let table = match value {
+        Value::Object(table) => table,
+        _ => unreachable!("`unrepresentable_values` reports the values that are not tables"),
+    };
+
+    let mut ini = Ini::new();
+    let mut sections: Vec<(&String, &Map<String, Value>)> = vec![];
+    for (key, value) in table.iter() {
+        match value {
+            Value::Object(section) => sections.push((key, section)),
+            _ => {
+                if let Some(s) = scalar_to_string(value) {
+                    ini.with_general_section().set(key.as_str(), s);
+                }
+            }
+        }
+    }
+
+    for (section_name, section) in sections {
+        // Make sure that empty structs still get their section header
+        ini.entry(Some(section_name.clone()))
+            .or_insert(Default::default());
+        for (key, value) in section.iter() {
+            if let Some(s) = scalar_to_string(value) {
+                ini.with_section(Some(section_name.as_str()))
+                    .set(key.as_str(), s);
+            }
+        }
+    }
+
+    let mut buf: Vec<u8> = vec![];
+    ini.write_to_policy(&mut buf, EscapePolicy::Reserved)
+        .map_err(serialization_failed)?;
+    String::from_utf8(buf).map_err(serialization_failed)
+}
+
+fn serialization_failed(e: impl std::error::Error + Send + Sync + 'static) -> ConfiggenError {
+    ConfiggenError::SerializationFailed {
+        format: SerializationFormat::Ini,
+        source: Box::new(e),
+    }
+}
+
+/// Parses an INI document into a format-neutral value tree
+///
+/// The keys of the general section are put at the top level, and each section becomes a nested
+/// table. All the values are strings, since INI does not carry any type information.
+pub fn parse_value(content: &str) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+    let ini = Ini::load_from_str(content)?;
+    let mut table = Map::new();
+    for (section, properties) in ini.iter() {
+        let properties: Map<String, Value> = properties
+            .iter()
+            .map(|(k, v)| (k.to_owned(), Value::String(v.to_owned())))
+            .collect();
+        match section {
+            None => table.extend(properties),
+            Some(name) => {
+                table.insert(name.to_owned(), Value::Object(properties));
+            }
+        }
+    }
+    Ok(Value::Object(table))
+}
+
+fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        // The sequences and the nested tables have been reported by `unrepresentable_values`
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{get_server_config, ServerConfig};
+    use crate::Violation;
+
+    #[derive(Serialize)]
+    struct TooDeepConfig {
+        pub outer: ServerConfig,
+    }
+
+    #[derive(Serialize)]
+    struct SequenceConfig {
+        pub values: Vec<i32>,
+    }
+
+    #[test]
+    pub fn test_sections_mapping() {
+        let mut config = get_server_config();
+        config.name = "test".to_owned();
+
+        let s = to_string(&config).unwrap();
+        let ini = Ini::load_from_str(&s).unwrap();
+
+        assert_eq!(ini.get_from(None::<String>, "name"), Some("test"));
+        assert_eq!(ini.get_from(None::<String>, "timeout"), None);
+        assert_eq!(ini.get_from(Some("server"), "host"), Some("localhost"));
+        assert_eq!(ini.get_from(Some("server"), "port"), Some("8080"));
+
+        let value = parse_value(&s).unwrap();
+        assert_eq!(value["name"], "test");
+        assert_eq!(value["server"]["port"], "8080");
+    }
+
+    #[test]
+    pub fn test_unrepresentable_nesting() {
+        let config = TooDeepConfig {
+            outer: get_server_config(),
+        };
+
+        let r = to_string(&config);
+        assert!(matches!(
+            r,
+            Err(ConfiggenError::UnrepresentableValues { ref values, .. })
+                if *values == vec![Violation::new("outer.server", "is a table nested deeper than one section")]
+        ));
+
+        let r = to_string(&SequenceConfig { values: vec![1, 2] });
+        assert!(matches!(
+            r,
+            Err(ConfiggenError::UnrepresentableValues { ref values, .. })
+                if *values == vec![Violation::new("values", "is a sequence")]
+        ));
+    }
+}
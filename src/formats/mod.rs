@@ -0,0 +1,217 @@
+pub mod comments;
+#[cfg(feature = "ini")]
+pub mod ini;
+#[cfg(feature = "ron")]
+pub mod ron;
+
+use std::error::Error;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::utils::read_configuration;
+use crate::Error as ConfiggenError;
+use crate::SerializationFormat;
+
+/// Serializes `config` into a string in the given `format`
+#[cfg_attr(
+    not(any(
+        feature = "json",
+        feature = "json5",
+        feature = "toml",
+        feature = "ron",
+        feature = "yaml",
+        feature = "ini"
+    )),
+    allow(unused_variables)
+)]
+pub fn to_string(
+    config: &impl Serialize,
+    format: SerializationFormat,
+) -> Result<String, ConfiggenError> {
+    let data: Result<String, Box<dyn Error + Send + Sync>> = match format {
+        #[cfg(feature = "json")]
+        SerializationFormat::Json => match serde_json::to_string(&config) {
+            Ok(s) => Ok(s),
+            Err(e) => Err(Box::new(e)),
+        },
+        #[cfg(feature = "json5")]
+        SerializationFormat::Json5 => match json5_rs::to_string(&config) {
+            Ok(s) => Ok(s),
+            Err(e) => Err(Box::new(e)),
+        },
+        #[cfg(feature = "toml")]
+        SerializationFormat::Toml => match toml::to_string(&config) {
+            Ok(s) => Ok(s),
+            Err(e) => Err(Box::new(e)),
+        },
+        #[cfg(feature = "ron")]
+        SerializationFormat::Ron => match ::ron::to_string(&config) {
+            Ok(s) => Ok(s),
+            Err(e) => Err(Box::new(e)),
+        },
+        #[cfg(feature = "yaml")]
+        SerializationFormat::Yaml => match serde_yaml::to_string(&config) {
+            Ok(s) => Ok(s),
+            Err(e) => Err(Box::new(e)),
+        },
+        #[cfg(feature = "ini")]
+        SerializationFormat::Ini => match ini::to_string(&config) {
+            Ok(s) => Ok(s),
+            Err(e) => return Err(e),
+        },
+        #[allow(unreachable_patterns)]
+        _ => Err(Box::new(unsupported_format())),
+    };
+
+    data.map_err(|e| ConfiggenError::SerializationFailed { format, source: e })
+}
+
+/// Serializes a format-neutral value tree into a string in the given `format`
+///
+/// Unlike `to_string`, `null` values nested in tables are left out for the formats that cannot
+/// express them (TOML and INI), since a missing key deserializes into `None` anyway, and RON
+/// tables are written as structs so that they can be read back into the configuration type.
+pub fn value_to_string(
+    value: &Value,
+    format: SerializationFormat,
+) -> Result<String, ConfiggenError> {
+    match format {
+        #[cfg(feature = "ron")]
+        SerializationFormat::Ron => Ok(ron::value_to_string(value)),
+        SerializationFormat::Toml | SerializationFormat::Ini => {
+            to_string(&without_null_entries(value), format)
+        }
+        _ => to_string(value, format),
+    }
+}
+
+/// Reads the file at `config_file_path` and parses it into a format-neutral value tree
+///
+/// # Returns
+/// * Ok(Value) if the file could be read and parsed
+/// * Err(ConfiggenError::ReadingFailed) if the file could not be read
+/// * Err(ConfiggenError::ParsingFailed) if the content of the file is not valid in `format`
+pub fn read_value(
+    config_file_path: &Path,
+    format: SerializationFormat,
+) -> Result<Value, ConfiggenError> {
+    let content = read_configuration(config_file_path)?;
+    parse_value(&content, format).map_err(|e| ConfiggenError::ParsingFailed {
+        path: config_file_path.to_path_buf(),
+        format,
+        source: e,
+    })
+}
+
+/// The key referencing the JSON Schema of a JSON or JSON5 configuration file
+pub(crate) const SCHEMA_KEY: &str = "$schema";
+
+/// Same as `read_value`, without the `$schema` key of JSON and JSON5 files
+///
+/// That key references the JSON Schema of the file rather than being part of the configuration :
+/// every path reading a configuration (as opposed to rewriting its file) goes through this
+/// function, so that the key neither breaks types denying unknown fields nor shows up in diffs.
+pub fn read_config_value(
+    config_file_path: &Path,
+    format: SerializationFormat,
+) -> Result<Value, ConfiggenError> {
+    let mut value = read_value(config_file_path, format)?;
+    if matches!(
+        format,
+        SerializationFormat::Json | SerializationFormat::Json5
+    ) {
+        if let Some(table) = value.as_object_mut() {
+            table.shift_remove(SCHEMA_KEY);
+        }
+    }
+    Ok(value)
+}
+
+/// Parses `content` into a format-neutral value tree
+///
+/// TOML datetimes, which have no counterpart in the value tree, are read as strings.
+#[cfg_attr(
+    not(any(
+        feature = "json",
+        feature = "json5",
+        feature = "toml",
+        feature = "ron",
+        feature = "yaml",
+        feature = "ini"
+    )),
+    allow(unused_variables)
+)]
+pub fn parse_value(
+    content: &str,
+    format: SerializationFormat,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    match format {
+        #[cfg(feature = "json")]
+        SerializationFormat::Json => Ok(serde_json::from_str(content)?),
+        #[cfg(feature = "json5")]
+        SerializationFormat::Json5 => Ok(json5_rs::from_str(content)?),
+        #[cfg(feature = "toml")]
+        SerializationFormat::Toml => Ok(without_toml_datetimes(toml::from_str(content)?)),
+        #[cfg(feature = "ron")]
+        SerializationFormat::Ron => ron::parse_value(content),
+        #[cfg(feature = "yaml")]
+        SerializationFormat::Yaml => Ok(serde_yaml::from_str(content)?),
+        #[cfg(feature = "ini")]
+        SerializationFormat::Ini => ini::parse_value(content),
+        #[allow(unreachable_patterns)]
+        _ => Err(Box::new(unsupported_format())),
+    }
+}
+
+fn unsupported_format() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Unsupported, "Could not serialize the default configuration (Haven't you forgot to enable the required feature ?)")
+}
+
+/// Returns the text of the TOML datetime that `table` wraps, if it is one of the tables that the
+/// `toml` crate deserializes the datetimes into
+#[cfg(feature = "toml")]
+pub(crate) fn toml_datetime(table: &serde_json::Map<String, Value>) -> Option<&str> {
+    const DATETIME_FIELD: &str = "$__toml_private_datetime";
+    match table.get(DATETIME_FIELD) {
+        Some(Value::String(datetime)) if table.len() == 1 => Some(datetime),
+        _ => None,
+    }
+}
+
+/// Replaces the tables wrapping the TOML datetimes by the text of the datetimes
+#[cfg(feature = "toml")]
+fn without_toml_datetimes(value: Value) -> Value {
+    match value {
+        Value::Object(table) => match toml_datetime(&table) {
+            Some(datetime) => Value::String(datetime.to_owned()),
+            None => Value::Object(
+                table
+                    .into_iter()
+                    .map(|(k, v)| (k, without_toml_datetimes(v)))
+                    .collect(),
+            ),
+        },
+        Value::Array(values) => {
+            Value::Array(values.into_iter().map(without_toml_datetimes).collect())
+        }
+        value => value,
+    }
+}
+
+/// Returns a copy of `value` without the `null` entries of its tables, that TOML and INI cannot
+/// hold, as the `None` fields skipped by their serializers
+pub(crate) fn without_null_entries(value: &Value) -> Value {
+    match value {
+        Value::Object(table) => Value::Object(
+            table
+                .iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), without_null_entries(v)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.iter().map(without_null_entries).collect()),
+        _ => value.clone(),
+    }
+}
@@ -0,0 +1,132 @@
+use std::error::Error;
+use std::fmt::Write;
+
+use serde_json::Value;
+
+/// Parses a RON document into a format-neutral value tree
+///
+/// Going through `ron::Value` is required, since RON structs cannot be deserialized directly
+/// into a `serde_json::Value`.
+pub fn parse_value(content: &str) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let value: ron::Value = ron::from_str(content)?;
+    Ok(serde_json::to_value(value)?)
+}
+
+/// Writes a format-neutral value tree as a RON document
+///
+/// Tables whose keys are all valid identifiers are written as structs, and the others as maps.
+/// The `implicit_some` extension is enabled so that optional values can be written as is, and
+/// `null` is written as `None`.
+pub fn value_to_string(value: &Value) -> String {
+    let mut output = String::from("#![enable(implicit_some)]\n");
+    write_value(&mut output, value);
+    output
+}
+
+fn write_value(output: &mut String, value: &Value) {
+    match value {
+        Value::Null => output.push_str("None"),
+        Value::Bool(b) => output.push_str(&b.to_string()),
+        Value::Number(n) => output.push_str(&n.to_string()),
+        Value::String(s) => write_string(output, s),
+        Value::Array(values) => {
+            output.push('[');
+            for (i, v) in values.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                write_value(output, v);
+            }
+            output.push(']');
+        }
+        Value::Object(table) => {
+            let is_struct = !table.is_empty() && table.keys().all(|k| is_identifier(k));
+            output.push(if is_struct { '(' } else { '{' });
+            for (i, (k, v)) in table.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                if is_struct {
+                    output.push_str(k);
+                } else {
+                    write_string(output, k);
+                }
+                output.push(':');
+                write_value(output, v);
+            }
+            output.push(if is_struct { ')' } else { '}' });
+        }
+    }
+}
+
+fn write_string(output: &mut String, s: &str) {
+    output.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(output, "\\u{{{:x}}}", c as u32);
+            }
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c == '_' || c.is_ascii_alphabetic() => {}
+        _ => return false,
+    }
+    chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Inner {
+        pub name: String,
+        pub ratio: f64,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct RonConfig {
+        pub inner: Inner,
+        pub list: Vec<Inner>,
+        pub maybe: Option<i32>,
+        pub nothing: Option<i32>,
+        pub labels: HashMap<String, String>,
+    }
+
+    #[test]
+    pub fn test_value_round_trip() {
+        let config = RonConfig {
+            inner: Inner {
+                name: "a \"quoted\"\nname".to_owned(),
+                ratio: 0.5,
+            },
+            list: vec![Inner {
+                name: "b".to_owned(),
+                ratio: 2.0,
+            }],
+            maybe: Some(3),
+            nothing: None,
+            labels: HashMap::from([("with space".to_owned(), "x".to_owned())]),
+        };
+
+        let value = parse_value(&ron::to_string(&config).unwrap()).unwrap();
+        let written = value_to_string(&value);
+        let read_config: RonConfig = ron::from_str(&written).unwrap();
+
+        assert_eq!(read_config, config);
+    }
+}
@@ -0,0 +1,357 @@
+use std::fs::{hard_link, remove_file, rename, File, OpenOptions};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::Error as ConfiggenError;
+
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Writes `data` into a new file at `path`, so that the file either does not exist or is complete
+///
+/// The data is written to a temporary file next to `path` and synced to disk, then hard-linked to
+/// `path`. Linking fails if `path` already exists, so when several processes race to create the
+/// same file, exactly one of them wins and the others get an `AlreadyExists` error.
+/// On filesystems that do not support hard links (`Unsupported` or `EPERM` errors), the file is
+/// created with `create_new` and written in place instead, which is still exclusive but not atomic.
+///
+/// # Arguments
+/// * `path` - The path of the file to create
+/// * `data` - The content of the file
+/// * `sync_parent_dir` - Whether the parent directory should be synced as well once the file is
+///   in place, so that the new directory entry itself is durable
+///
+/// # Returns
+/// * Ok(()) if the file has been created
+/// * Err(ConfiggenError::ConfigFileAlreadyExists) if `path` already exists
+/// * Err(ConfiggenError::FileCreationFailed) if the file cannot be created, or linked to `path`
+/// * Err(ConfiggenError::WritingFailed) if writing the data fails
+/// * Err(ConfiggenError::FlushFailed) if flushing or syncing the data to disk fails
+pub(crate) fn write_new(
+    path: &Path,
+    data: &[u8],
+    sync_parent_dir: bool,
+) -> Result<(), ConfiggenError> {
+    let temp_path = temp_path_for(path);
+    let file = create_new(&temp_path).map_err(|e| ConfiggenError::FileCreationFailed {
+        path: path.to_path_buf(),
+        source: e,
+    })?;
+
+    if let Err(e) = write_and_sync(path, file, data) {
+        let _ = remove_file(&temp_path);
+        return Err(e);
+    }
+
+    let linked = hard_link(&temp_path, path);
+    let _ = remove_file(&temp_path);
+    match linked {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            return Err(ConfiggenError::ConfigFileAlreadyExists {
+                path: path.to_path_buf(),
+                source: e,
+            })
+        }
+        Err(e) if links_unsupported(&e) => write_in_place(path, data)?,
+        Err(e) => {
+            return Err(ConfiggenError::FileCreationFailed {
+                path: path.to_path_buf(),
+                source: e,
+            })
+        }
+    }
+
+    if sync_parent_dir {
+        let parent = path.parent().unwrap_or(Path::new("."));
+        sync_dir(parent).map_err(|e| ConfiggenError::FlushFailed {
+            path: parent.to_path_buf(),
+            source: e,
+        })?;
+    }
+    Ok(())
+}
+
+/// Tells whether `hard_link` failed because the filesystem does not support hard links, which
+/// Linux and the BSDs report with `EPERM` (e.g. on FAT or some network filesystems)
+fn links_unsupported(e: &std::io::Error) -> bool {
+    #[cfg(unix)]
+    const EPERM: i32 = 1;
+
+    #[cfg(unix)]
+    if e.raw_os_error() == Some(EPERM) {
+        return true;
+    }
+    e.kind() == ErrorKind::Unsupported
+}
+
+/// Writes `data` into `path`, atomically replacing the file if it already exists
+///
+/// The data is written to a temporary file next to `path`, synced to disk, then renamed over
+/// `path`, so that readers either see the previous content or the new one. The permissions of the
+/// replaced file are kept.
+/// If `path` is a symbolic link (e.g. set up by a dotfile manager), the file it points to is
+/// replaced and the link is kept.
+pub(crate) fn replace(
+    path: &Path,
+    data: &[u8],
+    sync_parent_dir: bool,
+) -> Result<(), ConfiggenError> {
+    let target = resolve_links(path);
+    let temp_path = temp_path_for(&target);
+    let file = create_new(&temp_path).map_err(|e| ConfiggenError::FileCreationFailed {
+        path: path.to_path_buf(),
+        source: e,
+    })?;
+    if let Ok(metadata) = std::fs::metadata(&target) {
+        let _ = file.set_permissions(metadata.permissions());
+    }
+
+    if let Err(e) = write_and_sync(path, file, data) {
+        let _ = remove_file(&temp_path);
+        return Err(e);
+    }
+
+    if let Err(e) = rename(&temp_path, &target) {
+        let _ = remove_file(&temp_path);
+        return Err(ConfiggenError::WritingFailed {
+            path: path.to_path_buf(),
+            source: e,
+        });
+    }
+
+    if sync_parent_dir {
+        let parent = target.parent().unwrap_or(Path::new("."));
+        sync_dir(parent).map_err(|e| ConfiggenError::FlushFailed {
+            path: parent.to_path_buf(),
+            source: e,
+        })?;
+    }
+    Ok(())
+}
+
+/// Returns the path of the file that `path` points to if it is a symbolic link, following the
+/// whole chain of links, or `path` itself otherwise
+///
+/// The target of a dangling link is returned as is, so that replacing it creates the file.
+fn resolve_links(path: &Path) -> PathBuf {
+    let is_link = std::fs::symlink_metadata(path)
+        .map(|m| m.file_type().is_symlink())
+        .unwrap_or(false);
+    if !is_link {
+        return path.to_path_buf();
+    }
+    if let Ok(target) = std::fs::canonicalize(path) {
+        return target;
+    }
+    match std::fs::read_link(path) {
+        Ok(target) => path.parent().unwrap_or(Path::new("")).join(target),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// Copies the file at `path` to `<path>.bak`, or to `<path>.bak.<n>` with the first free `n` if
+/// that one is taken, without ever overwriting an existing backup
+///
+/// If `path` is a symbolic link, the backup is made next to the file it points to.
+///
+/// # Returns
+/// * Ok(PathBuf) containing the path of the backup
+/// * Err(ConfiggenError::BackupFailed) if the copy fails
+pub(crate) fn backup(path: &Path) -> Result<PathBuf, ConfiggenError> {
+    let backup_failed = |e| ConfiggenError::BackupFailed {
+        path: path.to_path_buf(),
+        source: e,
+    };
+    let path = &resolve_links(path);
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut n = 0;
+    loop {
+        let backup_path = match n {
+            0 => path.with_file_name(format!("{}.bak", file_name)),
+            n => path.with_file_name(format!("{}.bak.{}", file_name, n)),
+        };
+        n += 1;
+        let mut backup_file = match create_new(&backup_path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(backup_failed(e)),
+        };
+
+        let r = File::open(path)
+            .and_then(|mut original| std::io::copy(&mut original, &mut backup_file))
+            .and_then(|_| backup_file.sync_all());
+        if let Err(e) = r {
+            let _ = remove_file(&backup_path);
+            return Err(backup_failed(e));
+        }
+        return Ok(backup_path);
+    }
+}
+
+/// Fallback of `write_new` for filesystems without hard links
+fn write_in_place(path: &Path, data: &[u8]) -> Result<(), ConfiggenError> {
+    let file = match create_new(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            return Err(ConfiggenError::ConfigFileAlreadyExists {
+                path: path.to_path_buf(),
+                source: e,
+            })
+        }
+        Err(e) => {
+            return Err(ConfiggenError::FileCreationFailed {
+                path: path.to_path_buf(),
+                source: e,
+            })
+        }
+    };
+    if let Err(e) = write_and_sync(path, file, data) {
+        let _ = remove_file(path);
+        return Err(e);
+    }
+    Ok(())
+}
+
+fn create_new(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().write(true).create_new(true).open(path)
+}
+
+/// Writes `data` into `file` and syncs it, `path` being the configuration file it will end up as
+fn write_and_sync(path: &Path, file: File, data: &[u8]) -> Result<(), ConfiggenError> {
+    let flush_failed = |e| ConfiggenError::FlushFailed {
+        path: path.to_path_buf(),
+        source: e,
+    };
+
+    let mut writer = BufWriter::new(file);
+    writer
+        .write_all(data)
+        .map_err(|e| ConfiggenError::WritingFailed {
+            path: path.to_path_buf(),
+            source: e,
+        })?;
+    let file = writer
+        .into_inner()
+        .map_err(|e| flush_failed(e.into_error()))?;
+    file.sync_all().map_err(flush_failed)
+}
+
+/// Returns a hidden path next to `path`, unique to this process and call
+fn temp_path_for(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let counter = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        file_name,
+        std::process::id(),
+        counter
+    ))
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    // An empty parent means that `path` was relative to the current directory
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    File::open(dir)?.sync_all()
+}
+
+/// Directories cannot be opened as files on this platform, the link is the best we can do
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use temp_dir::TempDir;
+
+    #[test]
+    pub fn test_write_new_leaves_no_temp_file() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("config.toml");
+
+        write_new(&path, b"toto = 2", true).unwrap();
+        let r = write_new(&path, b"toto = 3", false);
+        assert!(matches!(
+            r,
+            Err(ConfiggenError::ConfigFileAlreadyExists { .. })
+        ));
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "toto = 2");
+        let entries = std::fs::read_dir(tmpdir.path()).unwrap().count();
+        assert_eq!(entries, 1);
+    }
+
+    #[test]
+    pub fn test_links_unsupported() {
+        assert!(links_unsupported(&ErrorKind::Unsupported.into()));
+        #[cfg(unix)]
+        assert!(links_unsupported(&std::io::Error::from_raw_os_error(1)));
+        assert!(!links_unsupported(&ErrorKind::NotFound.into()));
+        assert!(!links_unsupported(&ErrorKind::PermissionDenied.into()));
+    }
+
+    #[test]
+    pub fn test_replace_and_backup() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("config.toml");
+        write_new(&path, b"toto = 2", false).unwrap();
+
+        let first_backup = backup(&path).unwrap();
+        let second_backup = backup(&path).unwrap();
+        assert_eq!(first_backup, tmpdir.path().join("config.toml.bak"));
+        assert_eq!(second_backup, tmpdir.path().join("config.toml.bak.1"));
+
+        replace(&path, b"toto = 3", true).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "toto = 3");
+        assert_eq!(std::fs::read_to_string(&first_backup).unwrap(), "toto = 2");
+        assert_eq!(std::fs::read_dir(tmpdir.path()).unwrap().count(), 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_replace_and_backup_through_symlink() {
+        let tmpdir = TempDir::new().unwrap();
+        let real_dir = tmpdir.path().join("dotfiles");
+        std::fs::create_dir(&real_dir).unwrap();
+        let real_path = real_dir.join("real.toml");
+        let link_path = tmpdir.path().join("config.toml");
+        std::fs::write(&real_path, "toto = 2").unwrap();
+        std::os::unix::fs::symlink(&real_path, &link_path).unwrap();
+
+        let backup_path = backup(&link_path).unwrap();
+        assert_eq!(backup_path, real_dir.join("real.toml.bak"));
+        replace(&link_path, b"toto = 3", true).unwrap();
+
+        assert!(std::fs::symlink_metadata(&link_path)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(std::fs::read_to_string(&real_path).unwrap(), "toto = 3");
+        assert_eq!(std::fs::read_to_string(&backup_path).unwrap(), "toto = 2");
+        assert_eq!(std::fs::read_dir(&real_dir).unwrap().count(), 2);
+    }
+
+    #[test]
+    pub fn test_failed_write_leaves_no_file() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("missing_dir").join("config.toml");
+
+        let r = write_new(&path, b"toto = 2", false);
+        assert!(matches!(r, Err(ConfiggenError::FileCreationFailed { .. })));
+        assert!(!path.exists());
+    }
+}
@@ -1,118 +1,637 @@
-use std::error::Error;
-use std::fs::{create_dir, File};
-use std::io::{BufWriter, Write};
-use std::path::PathBuf;
+pub(crate) mod atomic_write;
 
+use std::fs::{create_dir, create_dir_all};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "toml-edit")]
+use crate::document::TomlDocument;
+use crate::formats;
+use crate::migration;
+#[cfg(feature = "schema")]
+use crate::schema;
+use crate::utils::config_file_source;
+use crate::value;
+use crate::AppConfig;
 use crate::DefaultConfig;
+use crate::Documented;
 use crate::Error as ConfiggenError;
-use crate::SerializationFormat;
+use crate::Validate;
+use crate::{InitializationOutcome, OverwritePolicy, SerializationFormat};
 
-#[cfg(feature = "json5")]
-use json5_rs;
-#[cfg(feature = "ron")]
-use ron;
+use config::Config;
+use serde::de::DeserializeOwned;
 use serde::Serialize;
-#[cfg(feature = "json")]
-use serde_json;
-#[cfg(feature = "toml")]
-use toml;
 
 /// Creates the configuration directory at `dir_to_create` path
 ///
+/// The missing parent directories are created as well, so that a path returned by
+/// `AppPaths::config_dir` can be passed directly even if e.g. `~/.config` does not exist yet.
+///
 /// # Arguments
 /// * `dir_to_create` - A PathBuf containing the path to the configuration dir to create
 ///
 /// # Returns
 /// * Ok(()) if the creation went fine
-/// * Err(std::io::ErrorKind::AlreadyExists) if the directory already exists
+/// * Err(ConfiggenError::ConfigDirectoryAlreadyExists) if the directory already exists, carrying
+///   the error returned by `std::fs::create_dir`
 /// * The error returned by `std::fs::create_dir` if it fails
 pub fn create_config_dir(dir_to_create: PathBuf) -> Result<(), ConfiggenError> {
-    if dir_to_create.exists() {
-        let source_error = std::io::Error::new(
-            std::io::ErrorKind::AlreadyExists,
-            "Config directory already exists",
-        );
+    if let Some(parent) = dir_to_create.parent() {
+        if let Err(e) = create_dir_all(parent) {
+            return Err(ConfiggenError::ConfigDirectoryCreationFailed {
+                path: parent.to_path_buf(),
+                source: e,
+            });
+        }
+    }
 
-        return Err(ConfiggenError::ConfigDirectoryAlreadyExists(source_error));
+    // Not checking `exists()` beforehand : `create_dir` fails atomically if the directory exists,
+    // which keeps concurrent callers from both believing they created it
+    match create_dir(&dir_to_create) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            Err(ConfiggenError::ConfigDirectoryAlreadyExists {
+                path: dir_to_create,
+                source: e,
+            })
+        }
+        Err(e) => Err(ConfiggenError::ConfigDirectoryCreationFailed {
+            path: dir_to_create,
+            source: e,
+        }),
     }
+}
+
+/// Options tweaking how `initialize_config_file_with_options` writes the configuration file
+#[derive(Debug, Clone, Default)]
+pub struct InitializationOptions {
+    /// Also sync the parent directory once the file has been renamed into place, so that the
+    /// directory entry survives a power loss as well
+    pub sync_parent_dir: bool,
+    /// With `initialize_documented_config_file_with_options`, write the documentation of the fields
+    /// into a `<stem>.docs.json` sidecar file when the format cannot hold comments (JSON)
+    pub docs_sidecar: bool,
+    /// The JSON Schema to write next to the configuration file (see `schema::write_schema`), so
+    /// that editors can validate and autocomplete it, typically set with `with_schema`
+    ///
+    /// The configuration file references the schema : through a `$schema` key for JSON and JSON5
+    /// (ignored by `load_config`), a `#:schema` directive (taplo) for TOML, and a
+    /// `yaml-language-server` modeline for YAML. The schema is rewritten even if the configuration
+    /// file is kept, so that it follows the changes of the configuration type.
+    #[cfg(feature = "schema")]
+    pub schema: Option<schemars::schema::RootSchema>,
+}
 
-    if let Err(e) = create_dir(dir_to_create) {
-        return Err(ConfiggenError::ConfigDirectoryCreationFailed(e));
+impl InitializationOptions {
+    /// Returns the options with `schema` set to the JSON Schema of `T`
+    #[cfg(feature = "schema")]
+    pub fn with_schema<T: schemars::JsonSchema>(self) -> Self {
+        InitializationOptions {
+            schema: Some(schemars::schema_for!(T)),
+            ..self
+        }
     }
-    Ok(())
 }
 
-/// Creates the configuration directory at `dir_to_create` path
+/// Serializes `config` into a new configuration file at `config_file_path`
+///
+/// The file is written atomically : the configuration is written to a temporary file in the same
+/// directory, synced to disk and then linked to `config_file_path`, so that the file either does
+/// not exist or is complete, even if the process crashes or the disk fills up mid-write.
+/// The creation is exclusive : if several processes try to create the same file at the same
+/// time, exactly one of them succeeds and the others get `ConfigFileAlreadyExists`.
+/// `config` is not validated, since its type is not required to implement `Validate` : use
+/// `initialize_validated_config_file` to check it before writing it.
 ///
 /// # Arguments
 /// * `config` - The default config to serialize
 /// * `config_file_path` - The path to the file where we want to save the configuration
 /// * `format` - a `SerializationFormat` value to tell which file format to use
+/// * `policy` - an `OverwritePolicy` value to tell what to do if the file already exists
 ///
 /// # Returns
-/// * Ok(()) if the serialization and the saving went fine
-/// * Err(std::io::ErrorKind::AlreadyExists) if the file already exists
+/// * Ok(InitializationOutcome) telling what has been done to the file
+/// * Err(ConfiggenError::ConfigFileAlreadyExists) if the file already exists and `policy` is
+///   `Fail`, carrying the `std::io::ErrorKind::AlreadyExists` error returned by the filesystem
+/// * Err(ConfiggenError::BackupFailed) if `policy` is `BackupThenOverwrite` and the existing file
+///   could not be copied
+/// * Any error returned by `utils::read_configuration`, or Err(ConfiggenError::ParsingFailed), if
+///   `policy` is `Merge` and the existing file could not be read
+/// * Err(ConfiggenError::UnrepresentableValues) listing all the values of the configuration that
+///   INI cannot express, if the format is `Ini`
 /// * Err(std::Box(std::io::ErrorKind::Unsupported)) if the format specified is not handled by one
-/// of the enabled features
-/// * Any error that is returned by `BufWriter::write` if the writing in the file fails
+///   of the enabled features
+/// * Err(ConfiggenError::FileCreationFailed) if the file cannot be created (e.g. permissions)
+/// * Err(ConfiggenError::WritingFailed) if writing the file fails
+/// * Err(ConfiggenError::FlushFailed) if flushing or syncing the file to disk fails
 pub fn initialize_config_file(
     config: &(impl DefaultConfig + Serialize),
-    config_file_path: &PathBuf,
-    format: SerializationFormat,
-) -> Result<(), ConfiggenError> {
-    if config_file_path.exists() {
-        let source_error =
-            std::io::Error::new(std::io::ErrorKind::AlreadyExists, "File already exists");
-        return Err(ConfiggenError::ConfigFileAlreadyExists(source_error));
-    }
-
-    let data : Result<String, Box<dyn Error + Send + Sync>> = match format {
-        #[cfg(feature = "json")]
-        SerializationFormat::Json =>  { match serde_json::to_string(&config) {
-            Ok(s) => Ok(s),
-            Err(e) => Err(Box::new(e))
-        } },
-        #[cfg(feature = "json5")]
-        SerializationFormat::Json5 => { match json5_rs::to_string(&config) {
-            Ok(s) => Ok(s),
-            Err(e) => Err(Box::new(e))
-        } },
-        #[cfg(feature = "toml")]
-        SerializationFormat::Toml => { match toml::to_string(&config){
-            Ok(s) => Ok(s),
-            Err(e) => Err(Box::new(e))
-        } },
-        #[cfg(feature = "ron")]
-        SerializationFormat::Ron => { match ron::to_string(&config) {
-            Ok(s) => Ok(s),
-            Err(e) => Err(Box::new(e))
-        } },
-        #[allow(unreachable_patterns)]
-        _ => Err(Box::new(std::io::Error::new(std::io::ErrorKind::Unsupported, "Could not serialize the default configuration (Haven't you forgot to enable the required feature ?)")))
+    config_file_path: &Path,
+    format: SerializationFormat,
+    policy: OverwritePolicy,
+) -> Result<InitializationOutcome, ConfiggenError> {
+    initialize_config_file_with_options(
+        config,
+        config_file_path,
+        format,
+        policy,
+        &InitializationOptions::default(),
+    )
+}
+
+/// Same as `initialize_config_file`, with `options` tweaking how the file is written
+pub fn initialize_config_file_with_options(
+    config: &(impl DefaultConfig + Serialize),
+    config_file_path: &Path,
+    format: SerializationFormat,
+    policy: OverwritePolicy,
+    options: &InitializationOptions,
+) -> Result<InitializationOutcome, ConfiggenError> {
+    let data = serialize_config(config, config_file_path, format, None, options)?;
+    write_config_file(config, &data, config_file_path, format, policy, options)
+}
+
+/// Same as `initialize_config_file`, the doc comments of the fields of `config` being written as
+/// comments into the file
+///
+/// Comments are written for the TOML (`toml-edit` feature), JSON5 and RON formats, the latter two
+/// being pretty-printed so that each key gets its own line. The other formats are written without
+/// comments, but the documentation can be written into a sidecar file for JSON (see
+/// `InitializationOptions::docs_sidecar`).
+/// The comments are only written into new files : keys added with `OverwritePolicy::Merge` are not
+/// documented.
+pub fn initialize_documented_config_file<T: DefaultConfig + Documented + Serialize>(
+    config: &T,
+    config_file_path: &Path,
+    format: SerializationFormat,
+    policy: OverwritePolicy,
+) -> Result<InitializationOutcome, ConfiggenError> {
+    initialize_documented_config_file_with_options(
+        config,
+        config_file_path,
+        format,
+        policy,
+        &InitializationOptions::default(),
+    )
+}
+
+/// Same as `initialize_documented_config_file`, with `options` tweaking how the file is written
+pub fn initialize_documented_config_file_with_options<T: DefaultConfig + Documented + Serialize>(
+    config: &T,
+    config_file_path: &Path,
+    format: SerializationFormat,
+    policy: OverwritePolicy,
+    options: &InitializationOptions,
+) -> Result<InitializationOutcome, ConfiggenError> {
+    let docs = T::field_docs();
+    let data = serialize_config(config, config_file_path, format, Some(&docs), options)?;
+    let outcome = write_config_file(config, &data, config_file_path, format, policy, options)?;
+
+    if options.docs_sidecar
+        && format == SerializationFormat::Json
+        && outcome != InitializationOutcome::Skipped
+    {
+        let docs: serde_json::Map<String, serde_json::Value> = docs
+            .into_iter()
+            .map(|(path, doc)| (path, serde_json::Value::String(doc)))
+            .collect();
+        let data = formats::to_string(&docs, format)?;
+        let sidecar_path = config_file_path.with_extension("docs.json");
+        atomic_write::replace(&sidecar_path, data.as_bytes(), options.sync_parent_dir)?;
+    }
+    Ok(outcome)
+}
+
+/// Serializes `config` in `format`, with the documentation `docs` as comments if any, and
+/// referencing the JSON Schema of `options` if any
+#[cfg_attr(not(feature = "schema"), allow(unused_variables))]
+pub(crate) fn serialize_config(
+    config: &impl Serialize,
+    config_file_path: &Path,
+    format: SerializationFormat,
+    docs: Option<&[(String, String)]>,
+    options: &InitializationOptions,
+) -> Result<String, ConfiggenError> {
+    #[cfg(feature = "schema")]
+    if options.schema.is_some() {
+        let reference = schema::schema_reference(config_file_path);
+        return match format {
+            SerializationFormat::Json | SerializationFormat::Json5 => {
+                let value = serde_json::to_value(config).map_err(|e| {
+                    ConfiggenError::SerializationFailed {
+                        format,
+                        source: Box::new(e),
+                    }
+                })?;
+                match value {
+                    serde_json::Value::Object(table) => {
+                        let mut with_schema = serde_json::Map::new();
+                        with_schema.insert(formats::SCHEMA_KEY.to_owned(), reference.into());
+                        with_schema.extend(table);
+                        serialize_with_docs(&with_schema, format, docs)
+                    }
+                    value => serialize_with_docs(&value, format, docs),
+                }
+            }
+            _ => {
+                let data = serialize_with_docs(config, format, docs)?;
+                match schema::schema_directive(&reference, format) {
+                    Some(directive) => Ok(directive + &data),
+                    None => Ok(data),
+                }
+            }
+        };
+    }
+    serialize_with_docs(config, format, docs)
+}
+
+fn serialize_with_docs(
+    config: &impl Serialize,
+    format: SerializationFormat,
+    docs: Option<&[(String, String)]>,
+) -> Result<String, ConfiggenError> {
+    match docs {
+        Some(docs) => formats::comments::to_documented_string(config, format, docs),
+        None => formats::to_string(config, format),
+    }
+}
+
+/// Writes the serialized `config`, `data`, into a new configuration file, applying `policy` if the
+/// file already exists, then writes the JSON Schema of `options` if any
+pub(crate) fn write_config_file(
+    config: &impl Serialize,
+    data: &str,
+    config_file_path: &Path,
+    format: SerializationFormat,
+    policy: OverwritePolicy,
+    options: &InitializationOptions,
+) -> Result<InitializationOutcome, ConfiggenError> {
+    let outcome = apply_policy(config, data, config_file_path, format, policy, options)?;
+    #[cfg(feature = "schema")]
+    if let Some(schema) = &options.schema {
+        schema::write_root_schema(schema, config_file_path, options.sync_parent_dir)?;
+    }
+    Ok(outcome)
+}
+
+fn apply_policy(
+    config: &impl Serialize,
+    data: &str,
+    config_file_path: &Path,
+    format: SerializationFormat,
+    policy: OverwritePolicy,
+    options: &InitializationOptions,
+) -> Result<InitializationOutcome, ConfiggenError> {
+    let already_exists =
+        match atomic_write::write_new(config_file_path, data.as_bytes(), options.sync_parent_dir) {
+            Ok(()) => return Ok(InitializationOutcome::Created),
+            Err(e @ ConfiggenError::ConfigFileAlreadyExists { .. }) => e,
+            Err(e) => return Err(e),
+        };
+
+    let replace = |data: &str| {
+        atomic_write::replace(config_file_path, data.as_bytes(), options.sync_parent_dir)
     };
+    match policy {
+        OverwritePolicy::Fail => Err(already_exists),
+        OverwritePolicy::Skip => Ok(InitializationOutcome::Skipped),
+        OverwritePolicy::Overwrite => {
+            replace(data)?;
+            Ok(InitializationOutcome::Overwritten)
+        }
+        OverwritePolicy::BackupThenOverwrite => {
+            let backup_path = atomic_write::backup(config_file_path)?;
+            replace(data)?;
+            Ok(InitializationOutcome::BackedUp(backup_path))
+        }
+        OverwritePolicy::Merge => {
+            let added =
+                upgrade_config_file_with_options(config, config_file_path, format, options)?;
+            match added.is_empty() {
+                true => Ok(InitializationOutcome::Skipped),
+                false => Ok(InitializationOutcome::Merged),
+            }
+        }
+    }
+}
+
+/// Same as `initialize_config_file`, but infers the serialization format from the extension of
+/// `config_file_path`
+///
+/// # Returns
+/// * Any error returned by `SerializationFormat::from_path` if the format cannot be inferred
+/// * Otherwise the same values as `initialize_config_file`
+pub fn initialize_config_file_from_extension(
+    config: &(impl DefaultConfig + Serialize),
+    config_file_path: &Path,
+    policy: OverwritePolicy,
+) -> Result<InitializationOutcome, ConfiggenError> {
+    let format = SerializationFormat::from_path(config_file_path)?;
+    initialize_config_file(config, config_file_path, format, policy)
+}
+
+/// Upgrades the existing configuration file at `config_file_path` with the keys of `config` it
+/// lacks, typically fields added to the configuration struct since the file has been generated
+///
+/// The keys are inserted recursively into nested tables, and the values set by the user are left
+/// untouched. The file is only rewritten (atomically) if at least one key is missing.
+/// With the `toml-edit` feature, TOML files are edited in place through a `TomlDocument`, keeping
+/// their comments and formatting.
+///
+/// # Arguments
+/// * `config` - The default config, whose keys are compared with the ones of the file
+/// * `config_file_path` - The path to the configuration file to upgrade
+/// * `format` - a `SerializationFormat` value to tell which file format is used
+///
+/// # Returns
+/// * Ok(Vec<String>) containing the dotted paths of the added keys (e.g. `server.timeout`)
+/// * Any error returned by `utils::read_configuration` if the file cannot be read
+/// * Err(ConfiggenError::ParsingFailed) if the file is not valid in `format`
+/// * Err(ConfiggenError::SerializationFailed) if `config` or the upgraded file cannot be serialized
+/// * The errors returned by `initialize_config_file` if writing the file fails
+pub fn upgrade_config_file(
+    config: &(impl DefaultConfig + Serialize),
+    config_file_path: &Path,
+    format: SerializationFormat,
+) -> Result<Vec<String>, ConfiggenError> {
+    upgrade_config_file_with_options(
+        config,
+        config_file_path,
+        format,
+        &InitializationOptions::default(),
+    )
+}
 
-    if let Err(e) = data {
-        return Err(ConfiggenError::SerializationFailed(e));
+/// Same as `upgrade_config_file`, with `options` tweaking how the file is written
+pub fn upgrade_config_file_with_options(
+    config: &(impl DefaultConfig + Serialize),
+    config_file_path: &Path,
+    format: SerializationFormat,
+    options: &InitializationOptions,
+) -> Result<Vec<String>, ConfiggenError> {
+    // TOML files are edited in place, so that the comments of the user survive the upgrade
+    #[cfg(feature = "toml-edit")]
+    if format == SerializationFormat::Toml {
+        let mut document = TomlDocument::open(config_file_path)?;
+        let added = document.insert_missing_defaults(config)?;
+        if !added.is_empty() {
+            let data = document.to_string();
+            atomic_write::replace(config_file_path, data.as_bytes(), options.sync_parent_dir)?;
+        }
+        return Ok(added);
     }
-    let data = data.unwrap();
 
-    let mut writer: BufWriter<File> = BufWriter::new(File::create(config_file_path).unwrap());
-    match writer.write(data.as_bytes()) {
-        Ok(_) => {
-            writer.flush().unwrap();
-            Ok(())
+    let mut existing = formats::read_value(config_file_path, format)?;
+    let defaults =
+        serde_json::to_value(config).map_err(|e| ConfiggenError::SerializationFailed {
+            format,
+            source: Box::new(e),
+        })?;
+
+    let added = value::merge_missing(&mut existing, &defaults);
+    if !added.is_empty() {
+        let data = formats::value_to_string(&existing, format)?;
+        atomic_write::replace(config_file_path, data.as_bytes(), options.sync_parent_dir)?;
+    }
+    Ok(added)
+}
+
+/// Reads the configuration file at `config_file_path` and deserializes it into a `T`
+///
+/// If migrations are registered for `T` (see `migration::Migrations::register`), an older file is
+/// migrated to the latest version first.
+/// The configuration is not validated, since `T` is not required to implement `Validate` : use
+/// `load_validated_config` to check it once loaded.
+///
+/// # Arguments
+/// * `config_file_path` - The path to the configuration file to read
+/// * `format` - a `SerializationFormat` value to tell which file format to parse
+///
+/// # Returns
+/// * Ok(T) if the file could be read and deserialized
+/// * Err(ConfiggenError::LoadingFailed) if the `config` crate failed to read or deserialize the file
+/// * Any error returned by `migration::migrate_config_file` if migrations are registered for `T`
+pub fn load_config<T: DeserializeOwned + 'static>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+) -> Result<T, ConfiggenError> {
+    migration::migrate_registered::<T>(config_file_path, format)?;
+    load_config_file(config_file_path, format)
+}
+
+/// Same as `load_config`, without migrating the file
+pub(crate) fn load_config_file<T: DeserializeOwned>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+) -> Result<T, ConfiggenError> {
+    let loading_failed = |e| ConfiggenError::LoadingFailed {
+        path: config_file_path.to_path_buf(),
+        source: e,
+    };
+
+    // JSON and JSON5 files are read without their `$schema` key, the `config` crate reading the
+    // other files (and the unreadable ones, reporting their errors) itself
+    let json_value = match format {
+        SerializationFormat::Json | SerializationFormat::Json5 => {
+            formats::read_config_value(config_file_path, format).ok()
         }
-        Err(e) => Err(ConfiggenError::WritingFailed(e)),
+        _ => None,
+    };
+    let builder = match json_value {
+        Some(value) => Config::builder().add_source(config::File::from_str(
+            &value.to_string(),
+            config::FileFormat::Json,
+        )),
+        None => Config::builder().add_source(config_file_source(config_file_path, format)),
+    };
+    let config = builder.build().map_err(loading_failed)?;
+    config.try_deserialize::<T>().map_err(loading_failed)
+}
+
+/// Same as `initialize_config_file`, `config` being validated first : nothing is written if it
+/// breaks any rule
+///
+/// # Returns
+/// * Err(ConfiggenError::ValidationFailed) carrying all the violations if `config` is not valid
+/// * Otherwise the same values as `initialize_config_file`
+pub fn initialize_validated_config_file(
+    config: &(impl DefaultConfig + Validate + Serialize),
+    config_file_path: &Path,
+    format: SerializationFormat,
+    policy: OverwritePolicy,
+) -> Result<InitializationOutcome, ConfiggenError> {
+    validate(config, config_file_path)?;
+    initialize_config_file(config, config_file_path, format, policy)
+}
+
+/// Same as `load_config`, the loaded configuration being validated
+///
+/// # Returns
+/// * Err(ConfiggenError::ValidationFailed) carrying all the violations if the configuration is not
+///   valid
+/// * Otherwise the same values as `load_config`
+pub fn load_validated_config<T: DeserializeOwned + Validate + 'static>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+) -> Result<T, ConfiggenError> {
+    let config: T = load_config(config_file_path, format)?;
+    validate(&config, config_file_path)?;
+    Ok(config)
+}
+
+fn validate(config: &impl Validate, config_file_path: &Path) -> Result<(), ConfiggenError> {
+    let violations = config.validate();
+    match violations.is_empty() {
+        true => Ok(()),
+        false => Err(ConfiggenError::ValidationFailed {
+            path: config_file_path.to_path_buf(),
+            violations,
+        }),
     }
 }
 
+/// Creates the configuration file from `default` if it does not exist yet, then loads it
+///
+/// Neither configuration is validated : use `load_or_init_validated` if `T` implements `Validate`.
+///
+/// # Arguments
+/// * `config_file_path` - The path to the configuration file
+/// * `format` - a `SerializationFormat` value to tell which file format to use
+/// * `default` - The default config to serialize if the file does not exist
+///
+/// # Returns
+/// * Ok((T, true)) if the file has just been created and then read
+/// * Ok((T, false)) if the file already existed and has been read
+/// * Any error returned by `initialize_config_file`
+/// * Any error returned by `load_config`
+pub fn load_or_init<T: DefaultConfig + Serialize + DeserializeOwned + 'static>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+    default: &T,
+) -> Result<(T, bool), ConfiggenError> {
+    let outcome = initialize_config_file(default, config_file_path, format, OverwritePolicy::Skip)?;
+    let created = outcome == InitializationOutcome::Created;
+
+    let config = load_config(config_file_path, format)?;
+    Ok((config, created))
+}
+
+/// Same as `load_or_init`, `default` being validated before being written and the loaded
+/// configuration after being read
+///
+/// # Returns
+/// * Err(ConfiggenError::ValidationFailed) carrying all the violations if `default` or the loaded
+///   configuration is not valid
+/// * Otherwise the same values as `load_or_init`
+pub fn load_or_init_validated<T>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+    default: &T,
+) -> Result<(T, bool), ConfiggenError>
+where
+    T: DefaultConfig + Validate + Serialize + DeserializeOwned + 'static,
+{
+    let outcome =
+        initialize_validated_config_file(default, config_file_path, format, OverwritePolicy::Skip)?;
+    let created = outcome == InitializationOutcome::Created;
+
+    let config = load_validated_config(config_file_path, format)?;
+    Ok((config, created))
+}
+
+/// Creates the configuration directory and file of `T`, as described by its `AppConfig`
+/// implementation, in one call
+///
+/// The missing directories are created, and the file is written from `T::default_config()` at
+/// `T::config_file_path()`.
+///
+/// # Arguments
+/// * `policy` - What to do if the configuration file already exists
+///
+/// # Returns
+/// * Ok((PathBuf, InitializationOutcome)) containing the path of the configuration file and what
+///   has been done to it
+/// * Err(ConfiggenError::HomeDirectoryNotFound) if the configuration directory cannot be resolved
+/// * Any error returned by `create_config_dir`, except `ConfigDirectoryAlreadyExists`
+/// * Any error returned by `initialize_config_file`
+pub fn initialize<T: AppConfig>(
+    policy: OverwritePolicy,
+) -> Result<(PathBuf, InitializationOutcome), ConfiggenError> {
+    let config_file_path = T::config_file_path()?;
+    let outcome = initialize_at::<T>(&config_file_path, policy)?;
+    Ok((config_file_path, outcome))
+}
+
+/// Creates the configuration directory and file of `T` if they do not exist yet, then loads the
+/// configuration file
+///
+/// The configuration is not validated : use `load_or_initialize_validated` if `T` implements
+/// `Validate`.
+///
+/// # Returns
+/// * Ok((T, true)) if the file has just been created and then read
+/// * Ok((T, false)) if the file already existed and has been read
+/// * Any error returned by `initialize`
+/// * Any error returned by `load_config`
+pub fn load_or_initialize<T: AppConfig + DeserializeOwned + 'static>(
+) -> Result<(T, bool), ConfiggenError> {
+    load_or_initialize_at(&T::config_file_path()?)
+}
+
+/// Same as `load_or_initialize`, `T::default_config()` being validated before being written and
+/// the loaded configuration after being read
+///
+/// # Returns
+/// * Err(ConfiggenError::ValidationFailed) carrying all the violations if the default or the
+///   loaded configuration is not valid
+/// * Otherwise the same values as `load_or_initialize`
+pub fn load_or_initialize_validated<T: AppConfig + DeserializeOwned + Validate + 'static>(
+) -> Result<(T, bool), ConfiggenError> {
+    load_or_initialize_validated_at(&T::config_file_path()?)
+}
+
+/// Writes `T::default_config()` at `config_file_path`, creating its parent directories first
+fn initialize_at<T: AppConfig>(
+    config_file_path: &Path,
+    policy: OverwritePolicy,
+) -> Result<InitializationOutcome, ConfiggenError> {
+    if let Some(config_dir) = config_file_path.parent() {
+        match create_config_dir(config_dir.to_path_buf()) {
+            Ok(()) | Err(ConfiggenError::ConfigDirectoryAlreadyExists { .. }) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    initialize_config_file(&T::default_config(), config_file_path, T::FORMAT, policy)
+}
+
+fn load_or_initialize_at<T: AppConfig + DeserializeOwned + 'static>(
+    config_file_path: &Path,
+) -> Result<(T, bool), ConfiggenError> {
+    let outcome = initialize_at::<T>(config_file_path, OverwritePolicy::Skip)?;
+    let created = outcome == InitializationOutcome::Created;
+
+    let config = load_config(config_file_path, T::FORMAT)?;
+    Ok((config, created))
+}
+
+fn load_or_initialize_validated_at<T: AppConfig + DeserializeOwned + Validate + 'static>(
+    config_file_path: &Path,
+) -> Result<(T, bool), ConfiggenError> {
+    validate(&T::default_config(), config_file_path)?;
+    let (config, created) = load_or_initialize_at::<T>(config_file_path)?;
+    validate(&config, config_file_path)?;
+    Ok((config, created))
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::fixtures::{get_server_config, ServerConfig};
     use crate::utils::read_configuration;
-    use config::Config;
     use serde::Deserialize;
+    use std::sync::{Arc, Barrier};
     use temp_dir::TempDir;
 
     #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -134,28 +653,176 @@ mod tests {
         (tmpdir, config_file_path, dummy_config)
     }
 
+    #[test]
+    pub fn test_create_config_dir() {
+        let tmpdir: TempDir = TempDir::new().unwrap();
+        let config_dir = tmpdir.path().join(".config").join("app");
+
+        let r = create_config_dir(config_dir.clone());
+        assert!(r.is_ok());
+        assert!(config_dir.is_dir());
+
+        let r = create_config_dir(config_dir);
+        assert!(matches!(
+            r,
+            Err(ConfiggenError::ConfigDirectoryAlreadyExists { .. })
+        ));
+    }
+
+    #[test]
+    pub fn test_concurrent_create_config_dir() {
+        let tmpdir: TempDir = TempDir::new().unwrap();
+        let config_dir = tmpdir.path().join("app");
+        let barrier = Arc::new(Barrier::new(8));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let config_dir = config_dir.clone();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    match create_config_dir(config_dir) {
+                        Ok(()) => Ok(()),
+                        Err(ConfiggenError::ConfigDirectoryAlreadyExists { source, .. }) => {
+                            Err(source.kind())
+                        }
+                        Err(e) => panic!("Unexpected error {}", e),
+                    }
+                })
+            })
+            .collect();
+        let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+        assert!(results
+            .iter()
+            .all(|r| r.is_ok() || *r == Err(ErrorKind::AlreadyExists)));
+    }
+
+    #[test]
+    pub fn test_concurrent_initialize_config_file() {
+        let (_tmpdir, config_file_path, _) = get_test_init_data();
+        let barrier = Arc::new(Barrier::new(8));
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let config_file_path = config_file_path.clone();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    let config = DummyConfig {
+                        toto: i,
+                        tata: 0,
+                        s: "test".to_owned(),
+                    };
+                    barrier.wait();
+                    let r = match initialize_config_file(
+                        &config,
+                        &config_file_path,
+                        SerializationFormat::Toml,
+                        OverwritePolicy::Fail,
+                    ) {
+                        Ok(_) => Ok(()),
+                        Err(ConfiggenError::ConfigFileAlreadyExists { source, .. }) => {
+                            Err(source.kind())
+                        }
+                        Err(e) => panic!("Unexpected error {}", e),
+                    };
+                    (config, r)
+                })
+            })
+            .collect();
+        let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        let winners: Vec<&DummyConfig> = results
+            .iter()
+            .filter(|(_, r)| r.is_ok())
+            .map(|(c, _)| c)
+            .collect();
+        assert_eq!(winners.len(), 1);
+        assert!(results
+            .iter()
+            .all(|(_, r)| r.is_ok() || *r == Err(ErrorKind::AlreadyExists)));
+
+        let config: String = read_configuration(&config_file_path).unwrap();
+        let read_config: DummyConfig = toml::from_str(&config).unwrap();
+        assert_eq!(&read_config, winners[0]);
+    }
+
     #[test]
     pub fn test_initialize_config_file_toml() {
         let (_tmpdir, config_file_path, dummy_config) = get_test_init_data();
 
-        let r = initialize_config_file(&dummy_config, &config_file_path, SerializationFormat::Toml);
+        let r = initialize_config_file(
+            &dummy_config,
+            &config_file_path,
+            SerializationFormat::Toml,
+            OverwritePolicy::Fail,
+        );
         assert!(r.is_ok());
 
-        let config: String = read_configuration(&config_file_path);
+        let config: String = read_configuration(&config_file_path).unwrap();
 
         let read_config: DummyConfig = toml::from_str(&config).unwrap();
 
         assert_eq!(read_config, dummy_config);
     }
 
+    #[test]
+    pub fn test_initialize_config_file_with_options() {
+        let (tmpdir, config_file_path, dummy_config) = get_test_init_data();
+        let options = InitializationOptions {
+            sync_parent_dir: true,
+            ..Default::default()
+        };
+
+        let r = initialize_config_file_with_options(
+            &dummy_config,
+            &config_file_path,
+            SerializationFormat::Toml,
+            OverwritePolicy::Fail,
+            &options,
+        );
+        assert!(r.is_ok());
+
+        let config: String = read_configuration(&config_file_path).unwrap();
+        let read_config: DummyConfig = toml::from_str(&config).unwrap();
+        assert_eq!(read_config, dummy_config);
+        // The temporary file has been renamed into place
+        assert_eq!(std::fs::read_dir(tmpdir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    pub fn test_initialize_config_file_creation_failure() {
+        let (tmpdir, _, dummy_config) = get_test_init_data();
+        let config_file_path = tmpdir.path().join("missing_dir").join("config");
+
+        let r = initialize_config_file(
+            &dummy_config,
+            &config_file_path,
+            SerializationFormat::Toml,
+            OverwritePolicy::Fail,
+        );
+        match r {
+            Err(ConfiggenError::FileCreationFailed { path, .. }) => {
+                assert_eq!(path, config_file_path)
+            }
+            _ => panic!("Unexpected result {:?}", r),
+        }
+    }
+
     #[test]
     pub fn test_initialize_config_file_json() {
         let (_tmpdir, config_file_path, dummy_config) = get_test_init_data();
 
-        let r = initialize_config_file(&dummy_config, &config_file_path, SerializationFormat::Json);
+        let r = initialize_config_file(
+            &dummy_config,
+            &config_file_path,
+            SerializationFormat::Json,
+            OverwritePolicy::Fail,
+        );
         assert!(r.is_ok());
 
-        let config: String = read_configuration(&config_file_path);
+        let config: String = read_configuration(&config_file_path).unwrap();
 
         let read_config: DummyConfig = serde_json::from_str(&config).unwrap();
 
@@ -166,11 +833,15 @@ mod tests {
     pub fn test_initialize_config_file_json5() {
         let (_tmpdir, config_file_path, dummy_config) = get_test_init_data();
 
-        let r =
-            initialize_config_file(&dummy_config, &config_file_path, SerializationFormat::Json5);
+        let r = initialize_config_file(
+            &dummy_config,
+            &config_file_path,
+            SerializationFormat::Json5,
+            OverwritePolicy::Fail,
+        );
         assert!(r.is_ok());
 
-        let config: String = read_configuration(&config_file_path);
+        let config: String = read_configuration(&config_file_path).unwrap();
 
         let read_config: DummyConfig = json5_rs::from_str(&config).unwrap();
 
@@ -181,21 +852,50 @@ mod tests {
     pub fn test_initialize_config_file_ron() {
         let (_tmpdir, config_file_path, dummy_config) = get_test_init_data();
 
-        let r = initialize_config_file(&dummy_config, &config_file_path, SerializationFormat::Ron);
+        let r = initialize_config_file(
+            &dummy_config,
+            &config_file_path,
+            SerializationFormat::Ron,
+            OverwritePolicy::Fail,
+        );
         assert!(r.is_ok());
 
-        let config: String = read_configuration(&config_file_path);
+        let config: String = read_configuration(&config_file_path).unwrap();
 
         let read_config: DummyConfig = ron::from_str(&config).unwrap();
 
         assert_eq!(read_config, dummy_config);
     }
 
+    #[test]
+    pub fn test_initialize_config_file_yaml() {
+        let (_tmpdir, config_file_path, dummy_config) = get_test_init_data();
+
+        let r = initialize_config_file(
+            &dummy_config,
+            &config_file_path,
+            SerializationFormat::Yaml,
+            OverwritePolicy::Fail,
+        );
+        assert!(r.is_ok());
+
+        let config: String = read_configuration(&config_file_path).unwrap();
+
+        let read_config: DummyConfig = serde_yaml::from_str(&config).unwrap();
+
+        assert_eq!(read_config, dummy_config);
+    }
+
     #[test]
     pub fn test_read_config_with_config_crate() {
         let (_tmpdir, config_file_path, dummy_config) = get_test_init_data();
 
-        let r = initialize_config_file(&dummy_config, &config_file_path, SerializationFormat::Toml);
+        let r = initialize_config_file(
+            &dummy_config,
+            &config_file_path,
+            SerializationFormat::Toml,
+            OverwritePolicy::Fail,
+        );
         assert!(r.is_ok());
 
         let f = config::File::new(config_file_path.to_str().unwrap(), config::FileFormat::Toml);
@@ -206,4 +906,510 @@ mod tests {
         let read_config = config.try_deserialize::<DummyConfig>().unwrap();
         assert_eq!(read_config, dummy_config);
     }
+
+    #[test]
+    pub fn test_read_yaml_config_with_config_crate() {
+        let (_tmpdir, config_file_path, dummy_config) = get_test_init_data();
+
+        let r = initialize_config_file(
+            &dummy_config,
+            &config_file_path,
+            SerializationFormat::Yaml,
+            OverwritePolicy::Fail,
+        );
+        assert!(r.is_ok());
+
+        let f = config_file_source(&config_file_path, SerializationFormat::Yaml);
+        let config = Config::builder().add_source(f).build().unwrap();
+        let read_config = config.try_deserialize::<DummyConfig>().unwrap();
+        assert_eq!(read_config, dummy_config);
+    }
+
+    #[test]
+    pub fn test_initialize_config_file_ini() {
+        let (_tmpdir, config_file_path, dummy_config) = get_test_init_data();
+
+        let r = initialize_config_file(
+            &dummy_config,
+            &config_file_path,
+            SerializationFormat::Ini,
+            OverwritePolicy::Fail,
+        );
+        assert!(r.is_ok());
+
+        let f = config_file_source(&config_file_path, SerializationFormat::Ini);
+        let config = Config::builder().add_source(f).build().unwrap();
+        let read_config = config.try_deserialize::<DummyConfig>().unwrap();
+        assert_eq!(read_config, dummy_config);
+    }
+
+    #[test]
+    pub fn test_initialize_config_file_ini_unrepresentable() {
+        #[derive(Serialize)]
+        struct ListConfig {
+            pub values: Vec<i32>,
+            pub tags: Vec<String>,
+        }
+        let (_tmpdir, config_file_path, _) = get_test_init_data();
+
+        let r = initialize_config_file(
+            &ListConfig {
+                values: vec![1],
+                tags: vec![],
+            },
+            &config_file_path,
+            SerializationFormat::Ini,
+            OverwritePolicy::Fail,
+        );
+        // All the offending values are reported, as with `conversion::convert_value`
+        match r {
+            Err(ConfiggenError::UnrepresentableValues { format, values }) => {
+                assert_eq!(format, SerializationFormat::Ini);
+                assert_eq!(
+                    values,
+                    vec![
+                        crate::Violation::new("values", "is a sequence"),
+                        crate::Violation::new("tags", "is a sequence"),
+                    ]
+                );
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+        assert!(!config_file_path.exists());
+    }
+
+    #[test]
+    pub fn test_load_or_init() {
+        let (_tmpdir, config_file_path, dummy_config) = get_test_init_data();
+
+        let (read_config, created) =
+            load_or_init(&config_file_path, SerializationFormat::Toml, &dummy_config).unwrap();
+        assert!(created);
+        assert_eq!(read_config, dummy_config);
+
+        let other_default = DummyConfig {
+            toto: 0,
+            tata: 0,
+            s: "other".to_owned(),
+        };
+        let (read_config, created) =
+            load_or_init(&config_file_path, SerializationFormat::Toml, &other_default).unwrap();
+        assert!(!created);
+        assert_eq!(read_config, dummy_config);
+    }
+
+    #[test]
+    pub fn test_load_config_invalid_file() {
+        let (_tmpdir, config_file_path, _) = get_test_init_data();
+        std::fs::write(&config_file_path, "toto = \"not a number\"").unwrap();
+
+        let r = load_config::<DummyConfig>(&config_file_path, SerializationFormat::Toml);
+        assert!(matches!(r, Err(ConfiggenError::LoadingFailed { .. })));
+    }
+
+    #[test]
+    pub fn test_initialize_config_file_from_extension() {
+        let (tmpdir, _, dummy_config) = get_test_init_data();
+        let config_file_path = tmpdir.path().join("config.ron");
+
+        let r = initialize_config_file_from_extension(
+            &dummy_config,
+            &config_file_path,
+            OverwritePolicy::Fail,
+        );
+        assert!(r.is_ok());
+
+        let config: String = read_configuration(&config_file_path).unwrap();
+        let read_config: DummyConfig = ron::from_str(&config).unwrap();
+        assert_eq!(read_config, dummy_config);
+
+        let config_file_path = tmpdir.path().join("config.cfg");
+        let r = initialize_config_file_from_extension(
+            &dummy_config,
+            &config_file_path,
+            OverwritePolicy::Fail,
+        );
+        assert!(matches!(
+            r,
+            Err(ConfiggenError::AmbiguousFormat { ref extension, .. }) if extension == "cfg"
+        ));
+        assert!(!config_file_path.exists());
+    }
+
+    #[test]
+    pub fn test_overwrite_policies() {
+        let (_tmpdir, config_file_path, dummy_config) = get_test_init_data();
+        let other_config = DummyConfig {
+            toto: 5,
+            tata: 6,
+            s: "other".to_owned(),
+        };
+        let read = |path: &Path| -> DummyConfig {
+            toml::from_str(&read_configuration(path).unwrap()).unwrap()
+        };
+
+        let r = initialize_config_file(
+            &dummy_config,
+            &config_file_path,
+            SerializationFormat::Toml,
+            OverwritePolicy::Skip,
+        );
+        assert_eq!(r, Ok(InitializationOutcome::Created));
+
+        let r = initialize_config_file(
+            &other_config,
+            &config_file_path,
+            SerializationFormat::Toml,
+            OverwritePolicy::Skip,
+        );
+        assert_eq!(r, Ok(InitializationOutcome::Skipped));
+        assert_eq!(read(&config_file_path), dummy_config);
+
+        let r = initialize_config_file(
+            &other_config,
+            &config_file_path,
+            SerializationFormat::Toml,
+            OverwritePolicy::Overwrite,
+        );
+        assert_eq!(r, Ok(InitializationOutcome::Overwritten));
+        assert_eq!(read(&config_file_path), other_config);
+
+        let r = initialize_config_file(
+            &dummy_config,
+            &config_file_path,
+            SerializationFormat::Toml,
+            OverwritePolicy::BackupThenOverwrite,
+        );
+        let backup_path = match r {
+            Ok(InitializationOutcome::BackedUp(p)) => p,
+            _ => panic!("Unexpected result {:?}", r),
+        };
+        assert_eq!(read(&config_file_path), dummy_config);
+        assert_eq!(read(&backup_path), other_config);
+    }
+
+    #[test]
+    pub fn test_merge_policy_toml() {
+        let (_tmpdir, config_file_path, _) = get_test_init_data();
+        std::fs::write(
+            &config_file_path,
+            "name = \"mine\"\n[server]\nport = 9000\n",
+        )
+        .unwrap();
+
+        let r = initialize_config_file(
+            &get_server_config(),
+            &config_file_path,
+            SerializationFormat::Toml,
+            OverwritePolicy::Merge,
+        );
+        assert_eq!(r, Ok(InitializationOutcome::Merged));
+
+        let read_config: ServerConfig =
+            toml::from_str(&read_configuration(&config_file_path).unwrap()).unwrap();
+        assert_eq!(read_config.name, "mine");
+        assert_eq!(read_config.server.host, "localhost");
+        assert_eq!(read_config.server.port, 9000);
+
+        // Nothing is left to add : the file is not rewritten
+        let content = read_configuration(&config_file_path).unwrap();
+        let r = initialize_config_file(
+            &get_server_config(),
+            &config_file_path,
+            SerializationFormat::Toml,
+            OverwritePolicy::Merge,
+        );
+        assert_eq!(r, Ok(InitializationOutcome::Skipped));
+        assert_eq!(read_configuration(&config_file_path).unwrap(), content);
+    }
+
+    #[test]
+    pub fn test_merge_policy_ron() {
+        let (_tmpdir, config_file_path, _) = get_test_init_data();
+        std::fs::write(&config_file_path, "(name: \"mine\", server: (port: 9000))").unwrap();
+
+        let r = initialize_config_file(
+            &get_server_config(),
+            &config_file_path,
+            SerializationFormat::Ron,
+            OverwritePolicy::Merge,
+        );
+        assert_eq!(r, Ok(InitializationOutcome::Merged));
+
+        let read_config: ServerConfig =
+            ron::from_str(&read_configuration(&config_file_path).unwrap()).unwrap();
+        assert_eq!(read_config.name, "mine");
+        assert_eq!(read_config.server.host, "localhost");
+        assert_eq!(read_config.server.port, 9000);
+    }
+
+    #[test]
+    pub fn test_upgrade_config_file() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct OldServer {
+            pub host: String,
+        }
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct OldConfig {
+            pub name: String,
+            pub server: OldServer,
+        }
+
+        let (_tmpdir, config_file_path, _) = get_test_init_data();
+        let old_config = OldConfig {
+            name: "mine".to_owned(),
+            server: OldServer {
+                host: "example.com".to_owned(),
+            },
+        };
+        for format in [
+            SerializationFormat::Json,
+            SerializationFormat::Json5,
+            SerializationFormat::Toml,
+            SerializationFormat::Ron,
+            SerializationFormat::Yaml,
+            SerializationFormat::Ini,
+        ] {
+            let _ = std::fs::remove_file(&config_file_path);
+            initialize_config_file(
+                &old_config,
+                &config_file_path,
+                format,
+                OverwritePolicy::Fail,
+            )
+            .unwrap();
+
+            let added =
+                upgrade_config_file(&get_server_config(), &config_file_path, format).unwrap();
+            assert_eq!(added, vec!["server.port"]);
+
+            let read_config: ServerConfig = load_config(&config_file_path, format).unwrap();
+            assert_eq!(read_config.name, "mine");
+            assert_eq!(read_config.server.host, "example.com");
+            assert_eq!(read_config.server.port, 8080);
+
+            let added =
+                upgrade_config_file(&get_server_config(), &config_file_path, format).unwrap();
+            assert!(added.is_empty());
+        }
+    }
+
+    #[cfg(feature = "toml-edit")]
+    #[test]
+    pub fn test_upgrade_config_file_keeps_toml_comments() {
+        let (_tmpdir, config_file_path, _) = get_test_init_data();
+        let annotated = "# Set by the operator\nname = \"mine\"\n\n[server]\nhost = \"example.com\" # behind the proxy\n";
+        std::fs::write(&config_file_path, annotated).unwrap();
+
+        let added = upgrade_config_file(
+            &get_server_config(),
+            &config_file_path,
+            SerializationFormat::Toml,
+        )
+        .unwrap();
+        assert_eq!(added, vec!["server.port"]);
+        assert_eq!(
+            std::fs::read_to_string(&config_file_path).unwrap(),
+            format!("{}port = 8080\n", annotated)
+        );
+    }
+
+    #[test]
+    pub fn test_upgrade_config_file_skips_null_defaults() {
+        let (_tmpdir, config_file_path, _) = get_test_init_data();
+        let config = get_server_config();
+        for format in [SerializationFormat::Toml, SerializationFormat::Ini] {
+            std::fs::write(&config_file_path, "").unwrap();
+            let added = upgrade_config_file(&config, &config_file_path, format).unwrap();
+            assert_eq!(added, vec!["name", "server"]);
+            // The `None` value is not written, and not reported as missing again
+            let added = upgrade_config_file(&config, &config_file_path, format).unwrap();
+            assert!(added.is_empty(), "{:?}", added);
+            let read_config: ServerConfig = load_config(&config_file_path, format).unwrap();
+            assert_eq!(read_config, config);
+        }
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct WorkersConfig {
+        pub workers: u32,
+    }
+
+    impl AppConfig for WorkersConfig {
+        const APP_NAME: &'static str = "configgen test app";
+        const FILE_NAME: &'static str = "settings";
+        const FORMAT: SerializationFormat = SerializationFormat::Json;
+
+        fn default_config() -> Self {
+            WorkersConfig { workers: 4 }
+        }
+    }
+
+    impl Validate for WorkersConfig {
+        fn validate(&self) -> Vec<crate::Violation> {
+            match self.workers {
+                0 => vec![crate::Violation::new("workers", "must not be 0")],
+                _ => vec![],
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_initialize() {
+        // The path is given explicitly rather than resolved, since changing the environment
+        // would race with the other tests
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("configgentestapp").join("settings.json");
+
+        let (config, created) = load_or_initialize_at::<WorkersConfig>(&path).unwrap();
+        assert_eq!(config, WorkersConfig::default_config());
+        assert!(created);
+
+        std::fs::write(&path, r#"{"workers": 8}"#).unwrap();
+        let (config, created) = load_or_initialize_at::<WorkersConfig>(&path).unwrap();
+        assert_eq!(config.workers, 8);
+        assert!(!created);
+
+        let r = initialize_at::<WorkersConfig>(&path, OverwritePolicy::Overwrite);
+        assert_eq!(r.unwrap(), InitializationOutcome::Overwritten);
+        assert_eq!(read_configuration(&path).unwrap(), r#"{"workers":4}"#);
+
+        assert!(WorkersConfig::config_file_path()
+            .unwrap()
+            .ends_with("configgentestapp/settings.json"));
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    pub fn test_initialize_config_file_with_schema() {
+        #[derive(Serialize, Deserialize, schemars::JsonSchema, PartialEq, Debug)]
+        #[serde(deny_unknown_fields)]
+        struct SchemaConfig {
+            pub port: u16,
+        }
+
+        let tmpdir = TempDir::new().unwrap();
+        let config = SchemaConfig { port: 8080 };
+        for (format, expected) in [
+            (
+                SerializationFormat::Json,
+                r#"{"$schema":"config.schema.json","port":8080}"#,
+            ),
+            (
+                SerializationFormat::Json5,
+                r#"{"$schema":"config.schema.json","port":8080}"#,
+            ),
+            (
+                SerializationFormat::Toml,
+                "#:schema config.schema.json\nport = 8080\n",
+            ),
+        ] {
+            let config_file_path = tmpdir.child(format!("config.{}", format));
+            let r = initialize_config_file_with_options(
+                &config,
+                &config_file_path,
+                format,
+                OverwritePolicy::Fail,
+                &InitializationOptions::default().with_schema::<SchemaConfig>(),
+            );
+            assert_eq!(r, Ok(InitializationOutcome::Created));
+            assert_eq!(read_configuration(&config_file_path).unwrap(), expected);
+
+            // The `$schema` key is not part of the configuration, even if unknown fields are denied
+            let read_config: SchemaConfig = load_config(&config_file_path, format).unwrap();
+            assert_eq!(read_config, config);
+        }
+
+        let schema = read_configuration(&tmpdir.child("config.schema.json")).unwrap();
+        let schema: serde_json::Value = serde_json::from_str(&schema).unwrap();
+        assert_eq!(schema["title"], "SchemaConfig");
+    }
+
+    #[test]
+    pub fn test_validated_config_file() {
+        let (_tmpdir, config_file_path, _) = get_test_init_data();
+        let mut config = get_server_config();
+        config.name = String::new();
+        config.server.port = 80;
+
+        let r = initialize_validated_config_file(
+            &config,
+            &config_file_path,
+            SerializationFormat::Toml,
+            OverwritePolicy::Fail,
+        );
+        match r {
+            Err(ConfiggenError::ValidationFailed { violations, .. }) => {
+                let paths: Vec<&str> = violations.iter().map(|v| v.path.as_str()).collect();
+                assert_eq!(paths, vec!["name", "server.port"]);
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+        assert!(!config_file_path.exists());
+
+        let r = initialize_validated_config_file(
+            &get_server_config(),
+            &config_file_path,
+            SerializationFormat::Toml,
+            OverwritePolicy::Fail,
+        );
+        assert_eq!(r, Ok(InitializationOutcome::Created));
+        let read_config: ServerConfig =
+            load_validated_config(&config_file_path, SerializationFormat::Toml).unwrap();
+        assert_eq!(read_config, get_server_config());
+
+        std::fs::write(
+            &config_file_path,
+            "name = \"mine\"\n\n[server]\nhost = \"example.com\"\nport = 80\n",
+        )
+        .unwrap();
+        let r = load_validated_config::<ServerConfig>(&config_file_path, SerializationFormat::Toml);
+        assert_eq!(
+            r.unwrap_err().to_string(),
+            format!(
+                "Validation of {} failed : `server.port` must be above 1024",
+                config_file_path.display()
+            )
+        );
+    }
+
+    #[test]
+    pub fn test_load_or_init_validated() {
+        let (tmpdir, config_file_path, _) = get_test_init_data();
+        let mut invalid = get_server_config();
+        invalid.server.port = 80;
+
+        let r = load_or_init_validated(&config_file_path, SerializationFormat::Toml, &invalid);
+        assert!(matches!(r, Err(ConfiggenError::ValidationFailed { .. })));
+        assert!(!config_file_path.exists());
+
+        let (config, created) = load_or_init_validated(
+            &config_file_path,
+            SerializationFormat::Toml,
+            &get_server_config(),
+        )
+        .unwrap();
+        assert!(created);
+        assert_eq!(config, get_server_config());
+
+        std::fs::write(
+            &config_file_path,
+            "name = \"mine\"\n\n[server]\nhost = \"example.com\"\nport = 80\n",
+        )
+        .unwrap();
+        let r = load_or_init_validated(
+            &config_file_path,
+            SerializationFormat::Toml,
+            &get_server_config(),
+        );
+        assert!(matches!(r, Err(ConfiggenError::ValidationFailed { .. })));
+
+        let path = tmpdir.child("configgentestapp").join("settings.json");
+        let (_, created) = load_or_initialize_validated_at::<WorkersConfig>(&path).unwrap();
+        assert!(created);
+        std::fs::write(&path, r#"{"workers": 0}"#).unwrap();
+        let r = load_or_initialize_validated_at::<WorkersConfig>(&path);
+        assert!(matches!(r, Err(ConfiggenError::ValidationFailed { .. })));
+    }
 }
@@ -1,10 +1,38 @@
+#[cfg(feature = "tokio")]
+pub mod asynchronous;
+pub mod conversion;
+pub mod diff;
+#[cfg(feature = "toml-edit")]
+pub mod document;
 pub mod enums;
+pub mod environment;
 pub mod errors;
+#[cfg(test)]
+mod fixtures;
+mod formats;
 pub mod initialization;
-//mod sync;
+pub mod migration;
+pub mod paths;
+#[cfg(feature = "schema")]
+pub mod schema;
+#[cfg(feature = "watch")]
+pub mod sync;
 pub mod traits;
 pub mod utils;
+mod value;
 
+#[cfg(feature = "toml-edit")]
+pub use document::TomlDocument;
+pub use enums::InitializationOutcome;
+pub use enums::OverwritePolicy;
 pub use enums::SerializationFormat;
 pub use errors::Error;
+pub use traits::AppConfig;
 pub use traits::DefaultConfig;
+pub use traits::Documented;
+pub use traits::{Validate, Violation};
+
+#[cfg(feature = "derive")]
+pub use configgen_derive::Documented;
+#[cfg(feature = "schema")]
+pub use schemars;
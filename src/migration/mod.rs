@@ -0,0 +1,504 @@
+use std::any::TypeId;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+#[cfg(feature = "toml-edit")]
+use crate::diff::{Change, ConfigDiff};
+#[cfg(feature = "toml-edit")]
+use crate::document::TomlDocument;
+use crate::formats;
+use crate::initialization::{atomic_write, load_config_file};
+use crate::Error as ConfiggenError;
+use crate::SerializationFormat;
+
+/// The top-level key holding the version of a configuration file, a file without it being at
+/// version 0
+pub const VERSION_KEY: &str = "version";
+
+/// The migrations registered with `Migrations::register`, by configuration type
+static REGISTRY: RwLock<Vec<(TypeId, Arc<Migrations>)>> = RwLock::new(Vec::new());
+
+type Step = dyn Fn(&mut Value) -> Result<(), Box<dyn Error + Send + Sync>> + Send + Sync;
+
+/// A step migrating a configuration from one version to the next
+///
+/// The step edits the configuration as a format-neutral value tree, the `version` key being
+/// updated afterwards.
+pub struct Migration {
+    from_version: u64,
+    step: Box<Step>,
+}
+
+impl Migration {
+    /// # Arguments
+    /// * `from_version` - The version migrated from, the step producing version `from_version + 1`
+    /// * `step` - The function editing the configuration
+    pub fn new<F>(from_version: u64, step: F) -> Self
+    where
+        F: Fn(&mut Value) -> Result<(), Box<dyn Error + Send + Sync>> + Send + Sync + 'static,
+    {
+        Migration {
+            from_version,
+            step: Box::new(step),
+        }
+    }
+}
+
+/// The migrations of a configuration file, up to its latest version
+///
+/// ```
+/// use configgen_rs::migration::{rename_key, Migration, Migrations};
+///
+/// let migrations = Migrations::new(2)
+///     .with(Migration::new(0, |config| {
+///         rename_key(config, "port", "server.port");
+///         Ok(())
+///     }))
+///     .with(Migration::new(1, |config| {
+///         rename_key(config, "name", "instance_name");
+///         Ok(())
+///     }));
+/// ```
+pub struct Migrations {
+    latest_version: u64,
+    steps: BTreeMap<u64, Migration>,
+}
+
+/// What has been done by `migrate_config_file`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// The version of the file before the migration
+    pub from_version: u64,
+    /// The version of the file after the migration
+    pub to_version: u64,
+    /// The path of the copy of the original file
+    pub backup_path: PathBuf,
+}
+
+impl Migrations {
+    /// # Arguments
+    /// * `latest_version` - The version of the configuration files written by the application
+    pub fn new(latest_version: u64) -> Self {
+        Migrations {
+            latest_version,
+            steps: BTreeMap::new(),
+        }
+    }
+
+    /// Registers `migration`, replacing the one registered from the same version if any
+    pub fn with(mut self, migration: Migration) -> Self {
+        self.steps.insert(migration.from_version, migration);
+        self
+    }
+
+    /// Returns the version of the configuration files written by the application
+    pub fn latest_version(&self) -> u64 {
+        self.latest_version
+    }
+
+    /// Registers the migrations as the ones of the configuration type `T`, replacing the ones
+    /// registered before if any
+    ///
+    /// The older files are then migrated automatically (see `migrate_config_file`) when they are
+    /// loaded as a `T` by `initialization::load_config`, and so by `load_or_init`,
+    /// `load_validated_config`, `sync::watch_config` and the other functions built upon it.
+    pub fn register<T: 'static>(self) {
+        let mut registry = REGISTRY.write().unwrap_or_else(|e| e.into_inner());
+        registry.retain(|(type_id, _)| *type_id != TypeId::of::<T>());
+        registry.push((TypeId::of::<T>(), Arc::new(self)));
+    }
+
+    /// Applies the migrations from `version` to the latest version to `config`, the configuration
+    /// file at `config_file_path`
+    fn apply(
+        &self,
+        config: &mut Value,
+        mut version: u64,
+        config_file_path: &Path,
+    ) -> Result<(), ConfiggenError> {
+        while version < self.latest_version {
+            let from_version = version;
+            let migration =
+                self.steps
+                    .get(&from_version)
+                    .ok_or_else(|| ConfiggenError::MissingMigration {
+                        path: config_file_path.to_path_buf(),
+                        from_version,
+                    })?;
+            let migration_failed = |e| ConfiggenError::MigrationFailed {
+                path: config_file_path.to_path_buf(),
+                from_version,
+                source: e,
+            };
+
+            (migration.step)(config).map_err(migration_failed)?;
+            version = from_version + 1;
+            match config {
+                Value::Object(table) => {
+                    table.insert(VERSION_KEY.to_owned(), Value::from(version));
+                }
+                _ => return Err(migration_failed("The configuration is not a table".into())),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Migrates the configuration file at `config_file_path` to the latest version of `migrations`
+///
+/// The file is left untouched if it is already at the latest version. Otherwise, the original file
+/// is backed up next to it (see `OverwritePolicy::BackupThenOverwrite`) and the migrated
+/// configuration is written back atomically. With the `toml-edit` feature, TOML files are edited in
+/// place, keeping their comments apart from the ones of the removed keys.
+///
+/// # Returns
+/// * Ok(None) if the file is already at the latest version
+/// * Ok(Some(MigrationReport)) if the file has been migrated
+/// * Any error returned by `utils::read_configuration` if the file cannot be read
+/// * Err(ConfiggenError::ParsingFailed) if the file, or its version, is not valid
+/// * Err(ConfiggenError::UnsupportedConfigVersion) if the file is newer than the latest version
+/// * Err(ConfiggenError::MissingMigration) if a step of the migration is not registered
+/// * Err(ConfiggenError::MigrationFailed) if a step of the migration fails
+/// * Err(ConfiggenError::BackupFailed) if the original file cannot be backed up
+/// * The errors returned by `initialization::initialize_config_file` if writing the file fails
+pub fn migrate_config_file(
+    config_file_path: &Path,
+    format: SerializationFormat,
+    migrations: &Migrations,
+) -> Result<Option<MigrationReport>, ConfiggenError> {
+    let mut config = formats::read_value(config_file_path, format)?;
+    let version = read_version(&config, config_file_path, format)?;
+    if version == migrations.latest_version {
+        return Ok(None);
+    }
+    if version > migrations.latest_version {
+        return Err(ConfiggenError::UnsupportedConfigVersion {
+            path: config_file_path.to_path_buf(),
+            version,
+            latest_version: migrations.latest_version,
+        });
+    }
+
+    let original = config.clone();
+    migrations.apply(&mut config, version, config_file_path)?;
+    let data = migrated_data(&original, &config, config_file_path, format)?;
+    let backup_path = atomic_write::backup(config_file_path)?;
+    atomic_write::replace(config_file_path, data.as_bytes(), false)?;
+
+    Ok(Some(MigrationReport {
+        from_version: version,
+        to_version: migrations.latest_version,
+        backup_path,
+    }))
+}
+
+/// Serializes the migrated configuration `config` of the file at `config_file_path`
+///
+/// With the `toml-edit` feature, TOML files are edited in place through a `TomlDocument`, so that
+/// the comments of the user survive the migration.
+#[cfg_attr(not(feature = "toml-edit"), allow(unused_variables))]
+fn migrated_data(
+    original: &Value,
+    config: &Value,
+    config_file_path: &Path,
+    format: SerializationFormat,
+) -> Result<String, ConfiggenError> {
+    #[cfg(feature = "toml-edit")]
+    if format == SerializationFormat::Toml {
+        let diff = ConfigDiff::between(original, config);
+        // The keys of a `TomlDocument` are dotted paths, made of bare keys
+        let bare_keys = diff
+            .changes()
+            .iter()
+            .all(|change| change.path().iter().all(|segment| !segment.contains('.')));
+        if bare_keys {
+            let mut document = TomlDocument::open(config_file_path)?;
+            let (removed, set): (Vec<&Change>, Vec<&Change>) = diff
+                .changes()
+                .iter()
+                .partition(|change| matches!(change, Change::Removed { .. }));
+            for change in removed {
+                document.remove(&change.dotted_path());
+            }
+            for change in set {
+                match change {
+                    Change::Added { value, .. } | Change::Changed { new: value, .. } => {
+                        if value.is_null() {
+                            document.remove(&change.dotted_path());
+                        } else {
+                            document.set(&change.dotted_path(), value)?;
+                        }
+                    }
+                    Change::Removed { .. } => (),
+                }
+            }
+            return Ok(document.to_string());
+        }
+    }
+    formats::value_to_string(config, format)
+}
+
+/// Migrates the configuration file at `config_file_path` with the migrations registered for `T`
+/// (see `Migrations::register`), if any
+///
+/// # Returns
+/// * Ok(None) if no migrations are registered for `T`, or the file is already at the latest
+///   version
+/// * Otherwise the same values as `migrate_config_file`
+pub(crate) fn migrate_registered<T: 'static>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+) -> Result<Option<MigrationReport>, ConfiggenError> {
+    let migrations = REGISTRY
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .find(|(type_id, _)| *type_id == TypeId::of::<T>())
+        .map(|(_, migrations)| migrations.clone());
+    match migrations {
+        Some(migrations) => migrate_config_file(config_file_path, format, &migrations),
+        None => Ok(None),
+    }
+}
+
+/// Migrates the configuration file at `config_file_path` if needed (see `migrate_config_file`),
+/// then loads it
+///
+/// # Returns
+/// * Ok((T, Option<MigrationReport>)) containing the configuration, and what has been done if the
+///   file had to be migrated
+/// * Any error returned by `migrate_config_file`
+/// * Any error returned by `initialization::load_config`
+pub fn load_migrated_config<T: DeserializeOwned>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+    migrations: &Migrations,
+) -> Result<(T, Option<MigrationReport>), ConfiggenError> {
+    let report = migrate_config_file(config_file_path, format, migrations)?;
+    let config = load_config_file(config_file_path, format)?;
+    Ok((config, report))
+}
+
+/// Moves the value at the dotted path `from` to the dotted path `to`, creating the missing tables
+/// on the way
+///
+/// # Returns
+/// * true if there was a value at `from`
+pub fn rename_key(config: &mut Value, from: &str, to: &str) -> bool {
+    let (parent_path, name) = match from.rsplit_once('.') {
+        Some((parent_path, name)) => (Some(parent_path), name),
+        None => (None, from),
+    };
+    let mut parent = &mut *config;
+    for segment in parent_path.into_iter().flat_map(|p| p.split('.')) {
+        parent = match parent.get_mut(segment) {
+            Some(child) => child,
+            None => return false,
+        };
+    }
+    let value = match parent.as_object_mut().and_then(|t| t.remove(name)) {
+        Some(value) => value,
+        None => return false,
+    };
+
+    let mut target = config;
+    for segment in to.split('.') {
+        if !target.is_object() {
+            *target = Value::Object(Default::default());
+        }
+        target = target
+            .as_object_mut()
+            .map(|t| t.entry(segment).or_insert(Value::Null))
+            .unwrap_or_else(|| unreachable!());
+    }
+    *target = value;
+    true
+}
+
+fn read_version(
+    config: &Value,
+    config_file_path: &Path,
+    format: SerializationFormat,
+) -> Result<u64, ConfiggenError> {
+    let version = match config.get(VERSION_KEY) {
+        None => return Ok(0),
+        Some(version) => version,
+    };
+    // INI files only hold strings
+    let parsed = match version {
+        Value::String(s) => s.parse().ok(),
+        version => version.as_u64(),
+    };
+    parsed.ok_or_else(|| ConfiggenError::ParsingFailed {
+        path: config_file_path.to_path_buf(),
+        format,
+        source: format!("`{}` is not a non-negative integer", VERSION_KEY).into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::Server;
+    use crate::initialization::load_config;
+    use serde::Deserialize;
+    use temp_dir::TempDir;
+
+    /// The latest version of the configuration
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct VersionedConfig {
+        pub version: u64,
+        pub instance_name: String,
+        pub server: Server,
+    }
+
+    const VERSION_0: &str = "name = \"mine\"\nport = 8080\n\n[server]\nhost = \"example.com\"\n";
+
+    fn get_migrations() -> Migrations {
+        Migrations::new(2)
+            .with(Migration::new(0, |config| {
+                rename_key(config, "port", "server.port");
+                Ok(())
+            }))
+            .with(Migration::new(1, |config| {
+                match rename_key(config, "name", "instance_name") {
+                    true => Ok(()),
+                    false => Err("`name` is missing".into()),
+                }
+            }))
+    }
+
+    #[test]
+    pub fn test_load_migrated_config() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("config.toml");
+        std::fs::write(&path, VERSION_0).unwrap();
+
+        let (config, report) = load_migrated_config::<VersionedConfig>(
+            &path,
+            SerializationFormat::Toml,
+            &get_migrations(),
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            VersionedConfig {
+                version: 2,
+                instance_name: "mine".to_owned(),
+                server: Server {
+                    host: "example.com".to_owned(),
+                    port: 8080,
+                },
+            }
+        );
+        let report = report.unwrap();
+        assert_eq!(report.from_version, 0);
+        assert_eq!(report.to_version, 2);
+        assert_eq!(
+            std::fs::read_to_string(&report.backup_path).unwrap(),
+            VERSION_0
+        );
+
+        let (_, report) = load_migrated_config::<VersionedConfig>(
+            &path,
+            SerializationFormat::Toml,
+            &get_migrations(),
+        )
+        .unwrap();
+        assert_eq!(report, None);
+    }
+
+    #[test]
+    pub fn test_registered_migrations() {
+        // A type of its own, so that the registration does not leak into the other tests
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct RegisteredConfig {
+            pub version: u64,
+            pub instance_name: String,
+            pub server: Server,
+        }
+
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("config.toml");
+        let commented = VERSION_0.replace("[server]", "# Edited by hand\n[server]");
+        std::fs::write(&path, &commented).unwrap();
+        get_migrations().register::<RegisteredConfig>();
+
+        let config: RegisteredConfig = load_config(&path, SerializationFormat::Toml).unwrap();
+        assert_eq!(config.version, 2);
+        assert_eq!(config.instance_name, "mine");
+        assert_eq!(config.server.port, 8080);
+        // The comments of the TOML file are kept
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "version = 2\ninstance_name = \"mine\"\n\n# Edited by hand\n[server]\nhost = \"example.com\"\nport = 8080\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(tmpdir.child("config.toml.bak")).unwrap(),
+            commented
+        );
+
+        // The types without registered migrations are loaded as is
+        std::fs::write(&path, VERSION_0).unwrap();
+        let r = load_config::<VersionedConfig>(&path, SerializationFormat::Toml);
+        assert!(matches!(r, Err(ConfiggenError::LoadingFailed { .. })));
+    }
+
+    #[test]
+    pub fn test_migrate_config_file_errors() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("config.json");
+
+        std::fs::write(&path, r#"{"version": 3}"#).unwrap();
+        let r = migrate_config_file(&path, SerializationFormat::Json, &get_migrations());
+        assert!(matches!(
+            r,
+            Err(ConfiggenError::UnsupportedConfigVersion { version: 3, .. })
+        ));
+
+        std::fs::write(&path, r#"{"version": "one"}"#).unwrap();
+        let r = migrate_config_file(&path, SerializationFormat::Json, &get_migrations());
+        assert!(matches!(r, Err(ConfiggenError::ParsingFailed { .. })));
+
+        std::fs::write(&path, r#"{"version": 1}"#).unwrap();
+        let r = migrate_config_file(&path, SerializationFormat::Json, &get_migrations());
+        assert!(matches!(
+            r,
+            Err(ConfiggenError::MigrationFailed {
+                from_version: 1,
+                ..
+            })
+        ));
+
+        let migrations = Migrations::new(1);
+        let r = migrate_config_file(&path, SerializationFormat::Json, &migrations);
+        assert_eq!(r, Ok(None));
+        let migrations = Migrations::new(2);
+        let r = migrate_config_file(&path, SerializationFormat::Json, &migrations);
+        assert!(matches!(
+            r,
+            Err(ConfiggenError::MissingMigration {
+                from_version: 1,
+                ..
+            })
+        ));
+
+        // Failed migrations leave the file alone
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), r#"{"version": 1}"#);
+        assert!(!tmpdir.child("config.json.bak").exists());
+    }
+
+    #[test]
+    pub fn test_rename_key() {
+        let mut config = serde_json::json!({"a": {"b": 1}, "c": 2});
+        assert!(rename_key(&mut config, "a.b", "d.e"));
+        assert!(rename_key(&mut config, "c", "a.c"));
+        assert!(!rename_key(&mut config, "x.y", "z"));
+        assert_eq!(config, serde_json::json!({"a": {"c": 2}, "d": {"e": 1}}));
+    }
+}
@@ -0,0 +1,221 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use crate::Error as ConfiggenError;
+use crate::SerializationFormat;
+
+/// Resolves the platform-standard directories of an application
+///
+/// On Linux (and other non-Apple Unixes), the directories follow the XDG Base Directory
+/// specification : `$XDG_CONFIG_HOME`, `$XDG_DATA_HOME`, `$XDG_CACHE_HOME` and `$XDG_STATE_HOME`
+/// are honoured when they are set to an absolute path, and `~/.config`, `~/.local/share`,
+/// `~/.cache` and `~/.local/state` are used otherwise.
+/// On macOS the directories are located under `~/Library`, and on Windows under `%APPDATA%` and
+/// `%LOCALAPPDATA%`.
+///
+/// # Example
+/// ```no_run
+/// use configgen_rs::paths::AppPaths;
+/// use configgen_rs::SerializationFormat;
+///
+/// let paths = AppPaths::new("org", "My Company", "My App");
+/// configgen_rs::initialization::create_config_dir(paths.config_dir().unwrap()).ok();
+/// let config_file_path = paths
+///     .config_file_path("config", SerializationFormat::Toml)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppPaths {
+    qualifier: String,
+    organization: String,
+    application: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DirKind {
+    Config,
+    Data,
+    Cache,
+    State,
+}
+
+impl AppPaths {
+    /// # Arguments
+    /// * `qualifier` - The reverse domain name qualifier of the application (e.g. `com`, `org`),
+    ///   only used on macOS
+    /// * `organization` - The name of the organization developing the application, unused on
+    ///   Linux
+    /// * `application` - The name of the application
+    pub fn new(qualifier: &str, organization: &str, application: &str) -> Self {
+        AppPaths {
+            qualifier: qualifier.to_owned(),
+            organization: organization.to_owned(),
+            application: application.to_owned(),
+        }
+    }
+
+    /// Returns the directory where the configuration files of the application are stored
+    pub fn config_dir(&self) -> Result<PathBuf, ConfiggenError> {
+        self.resolve(DirKind::Config, &system_env)
+    }
+
+    /// Returns the directory where the data files of the application are stored
+    pub fn data_dir(&self) -> Result<PathBuf, ConfiggenError> {
+        self.resolve(DirKind::Data, &system_env)
+    }
+
+    /// Returns the directory where the cache files of the application are stored
+    pub fn cache_dir(&self) -> Result<PathBuf, ConfiggenError> {
+        self.resolve(DirKind::Cache, &system_env)
+    }
+
+    /// Returns the directory where the state files (logs, history...) of the application are
+    /// stored
+    pub fn state_dir(&self) -> Result<PathBuf, ConfiggenError> {
+        self.resolve(DirKind::State, &system_env)
+    }
+
+    /// Returns the path of the configuration file named `file_stem` in the configuration
+    /// directory, with the extension matching `format`
+    pub fn config_file_path(
+        &self,
+        file_stem: &str,
+        format: SerializationFormat,
+    ) -> Result<PathBuf, ConfiggenError> {
+        let mut path = self.config_dir()?;
+        path.push(format!("{}.{}", file_stem, format.extension()));
+        Ok(path)
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn resolve(
+        &self,
+        kind: DirKind,
+        env: &dyn Fn(&str) -> Option<OsString>,
+    ) -> Result<PathBuf, ConfiggenError> {
+        let (variable, fallback) = match kind {
+            DirKind::Config => ("XDG_CONFIG_HOME", ".config"),
+            DirKind::Data => ("XDG_DATA_HOME", ".local/share"),
+            DirKind::Cache => ("XDG_CACHE_HOME", ".cache"),
+            DirKind::State => ("XDG_STATE_HOME", ".local/state"),
+        };
+
+        // The specification requires relative paths to be ignored
+        let base = match env(variable).map(PathBuf::from) {
+            Some(p) if p.is_absolute() => p,
+            _ => home_dir(env)?.join(fallback),
+        };
+        Ok(base.join(application_dir_name(&self.application)))
+    }
+
+    #[cfg(target_os = "macos")]
+    fn resolve(
+        &self,
+        kind: DirKind,
+        env: &dyn Fn(&str) -> Option<OsString>,
+    ) -> Result<PathBuf, ConfiggenError> {
+        let base = match kind {
+            DirKind::Config | DirKind::Data | DirKind::State => "Library/Application Support",
+            DirKind::Cache => "Library/Caches",
+        };
+        let bundle_id = [&self.qualifier, &self.organization, &self.application]
+            .iter()
+            .filter(|s| !s.is_empty())
+            .map(|s| s.trim().replace(' ', "-"))
+            .collect::<Vec<String>>()
+            .join(".");
+        Ok(home_dir(env)?.join(base).join(bundle_id))
+    }
+
+    #[cfg(windows)]
+    fn resolve(
+        &self,
+        kind: DirKind,
+        env: &dyn Fn(&str) -> Option<OsString>,
+    ) -> Result<PathBuf, ConfiggenError> {
+        let (variable, leaf) = match kind {
+            DirKind::Config => ("APPDATA", "config"),
+            DirKind::Data => ("APPDATA", "data"),
+            DirKind::Cache => ("LOCALAPPDATA", "cache"),
+            DirKind::State => ("LOCALAPPDATA", "data"),
+        };
+        let base = env(variable)
+            .map(PathBuf::from)
+            .ok_or(ConfiggenError::HomeDirectoryNotFound)?;
+        Ok(base
+            .join(&self.organization)
+            .join(&self.application)
+            .join(leaf))
+    }
+}
+
+fn system_env(variable: &str) -> Option<OsString> {
+    std::env::var_os(variable)
+}
+
+#[cfg(unix)]
+fn home_dir(env: &dyn Fn(&str) -> Option<OsString>) -> Result<PathBuf, ConfiggenError> {
+    match env("HOME") {
+        Some(home) if !home.is_empty() => Ok(PathBuf::from(home)),
+        _ => Err(ConfiggenError::HomeDirectoryNotFound),
+    }
+}
+
+/// Turns "My App" into "myapp", the usual spelling of application directories on Linux
+#[cfg(all(unix, not(target_os = "macos")))]
+fn application_dir_name(application: &str) -> String {
+    application.trim().to_lowercase().replace(' ', "")
+}
+
+#[cfg(all(test, unix, not(target_os = "macos")))]
+mod tests {
+    use super::*;
+
+    fn env_from(
+        vars: &'static [(&'static str, &'static str)],
+    ) -> impl Fn(&str) -> Option<OsString> {
+        move |name| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| OsString::from(v))
+        }
+    }
+
+    #[test]
+    pub fn test_xdg_defaults() {
+        let paths = AppPaths::new("org", "Acme", "My App");
+        let env = env_from(&[("HOME", "/home/user")]);
+
+        let dir = paths.resolve(DirKind::Config, &env).unwrap();
+        assert_eq!(dir, PathBuf::from("/home/user/.config/myapp"));
+        let dir = paths.resolve(DirKind::Data, &env).unwrap();
+        assert_eq!(dir, PathBuf::from("/home/user/.local/share/myapp"));
+        let dir = paths.resolve(DirKind::Cache, &env).unwrap();
+        assert_eq!(dir, PathBuf::from("/home/user/.cache/myapp"));
+        let dir = paths.resolve(DirKind::State, &env).unwrap();
+        assert_eq!(dir, PathBuf::from("/home/user/.local/state/myapp"));
+    }
+
+    #[test]
+    pub fn test_xdg_overrides() {
+        let paths = AppPaths::new("org", "Acme", "myapp");
+        let env = env_from(&[
+            ("HOME", "/home/user"),
+            ("XDG_CONFIG_HOME", "/etc/xdg"),
+            ("XDG_CACHE_HOME", "relative/cache"),
+        ]);
+
+        let dir = paths.resolve(DirKind::Config, &env).unwrap();
+        assert_eq!(dir, PathBuf::from("/etc/xdg/myapp"));
+        // Relative paths are invalid according to the specification
+        let dir = paths.resolve(DirKind::Cache, &env).unwrap();
+        assert_eq!(dir, PathBuf::from("/home/user/.cache/myapp"));
+    }
+
+    #[test]
+    pub fn test_missing_home() {
+        let paths = AppPaths::new("org", "Acme", "myapp");
+        let r = paths.resolve(DirKind::Config, &env_from(&[]));
+        assert_eq!(r, Err(ConfiggenError::HomeDirectoryNotFound));
+    }
+}
@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+
+use schemars::schema::RootSchema;
+use schemars::{schema_for, JsonSchema};
+
+use crate::initialization::atomic_write;
+use crate::Error as ConfiggenError;
+use crate::SerializationFormat;
+
+/// Returns the path of the JSON Schema describing the configuration file at `config_file_path`,
+/// e.g. `config.schema.json` for `config.toml`
+pub fn schema_path(config_file_path: &Path) -> PathBuf {
+    config_file_path.with_extension("schema.json")
+}
+
+/// Generates the JSON Schema of the configuration type `T`
+///
+/// # Returns
+/// * Ok(String) containing the pretty-printed schema
+/// * Err(ConfiggenError::SerializationFailed) if the schema cannot be serialized
+pub fn to_schema_string<T: JsonSchema>() -> Result<String, ConfiggenError> {
+    root_schema_to_string(&schema_for!(T))
+}
+
+fn root_schema_to_string(schema: &RootSchema) -> Result<String, ConfiggenError> {
+    serde_json::to_string_pretty(schema).map_err(|e| ConfiggenError::SerializationFailed {
+        format: SerializationFormat::Json,
+        source: Box::new(e),
+    })
+}
+
+/// Writes the JSON Schema of `T` next to the configuration file at `config_file_path` (see
+/// `schema_path`), replacing the existing one if any so that it follows the changes of `T`
+///
+/// # Returns
+/// * Ok(PathBuf) containing the path of the schema
+/// * Err(ConfiggenError::SerializationFailed) if the schema cannot be serialized
+/// * The errors returned by `initialization::initialize_config_file` if writing the file fails
+pub fn write_schema<T: JsonSchema>(
+    config_file_path: &Path,
+    sync_parent_dir: bool,
+) -> Result<PathBuf, ConfiggenError> {
+    write_root_schema(&schema_for!(T), config_file_path, sync_parent_dir)
+}
+
+/// Same as `write_schema`, with an already generated schema
+pub(crate) fn write_root_schema(
+    schema: &RootSchema,
+    config_file_path: &Path,
+    sync_parent_dir: bool,
+) -> Result<PathBuf, ConfiggenError> {
+    let path = schema_path(config_file_path);
+    let data = root_schema_to_string(schema)?;
+    atomic_write::replace(&path, data.as_bytes(), sync_parent_dir)?;
+    Ok(path)
+}
+
+/// Returns the reference to the schema of the configuration file at `config_file_path`, relative
+/// to the file
+pub(crate) fn schema_reference(config_file_path: &Path) -> String {
+    schema_path(config_file_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Returns the line telling editors where the schema of a configuration file written in `format`
+/// is, if the format has a convention for it apart from a `$schema` key
+///
+/// # Arguments
+/// * `reference` - The path or URL of the schema, relative to the configuration file
+pub(crate) fn schema_directive(reference: &str, format: SerializationFormat) -> Option<String> {
+    match format {
+        // https://taplo.tamasfe.dev/configuration/directives.html
+        SerializationFormat::Toml => Some(format!("#:schema {}\n", reference)),
+        SerializationFormat::Yaml => {
+            Some(format!("# yaml-language-server: $schema={}\n", reference))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct ServerConfig {
+        /// Port to listen on
+        pub port: u16,
+        pub name: Option<String>,
+    }
+
+    #[test]
+    pub fn test_schema_path() {
+        assert_eq!(
+            schema_path(Path::new("/etc/app/config.toml")),
+            PathBuf::from("/etc/app/config.schema.json")
+        );
+    }
+
+    #[test]
+    pub fn test_to_schema_string() {
+        let schema: serde_json::Value =
+            serde_json::from_str(&to_schema_string::<ServerConfig>().unwrap()).unwrap();
+        assert_eq!(schema["title"], "ServerConfig");
+        assert_eq!(schema["required"], serde_json::json!(["port"]));
+        assert_eq!(
+            schema["properties"]["port"]["description"],
+            "Port to listen on"
+        );
+    }
+}
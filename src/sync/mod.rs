@@ -0,0 +1,380 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use arc_swap::ArcSwap;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::de::DeserializeOwned;
+
+use crate::initialization::{load_config, load_validated_config};
+use crate::Error as ConfiggenError;
+use crate::SerializationFormat;
+use crate::Validate;
+
+/// A shared handle on the latest configuration loaded by a `ConfigWatcher`
+///
+/// Cloning the handle is cheap, and `load` never blocks : the handle can be read from any thread
+/// as often as needed.
+#[derive(Debug)]
+pub struct ConfigHandle<T> {
+    current: Arc<ArcSwap<T>>,
+}
+
+impl<T> ConfigHandle<T> {
+    /// Creates a handle holding `config`, e.g. to hand a fixed configuration to code expecting a
+    /// handle, such as tests
+    pub fn new(config: T) -> Self {
+        ConfigHandle {
+            current: Arc::new(ArcSwap::from_pointee(config)),
+        }
+    }
+
+    /// Returns the latest configuration
+    pub fn load(&self) -> Arc<T> {
+        self.current.load_full()
+    }
+
+    fn store(&self, config: Arc<T>) {
+        self.current.store(config)
+    }
+}
+
+impl<T> Clone for ConfigHandle<T> {
+    fn clone(&self) -> Self {
+        ConfigHandle {
+            current: self.current.clone(),
+        }
+    }
+}
+
+/// What happened when the watched configuration file changed
+#[derive(Debug)]
+pub enum ConfigEvent<T> {
+    /// The file has been reloaded, and the new configuration published through the handle
+    Reloaded(Arc<T>),
+    /// The file could not be loaded, or is not valid : the previous configuration is kept
+    Rejected(ConfiggenError),
+}
+
+/// Options tweaking how `watch_config` reloads the configuration file
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// How long the file must stay untouched before being reloaded, so that the bursts of writes
+    /// of editors and of `initialize_config_file` trigger a single reload
+    pub debounce: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        WatchOptions {
+            debounce: Duration::from_millis(200),
+        }
+    }
+}
+
+/// Watches a configuration file, reloading it whenever it changes
+///
+/// The reloads happen on a background thread, which stops when the watcher is dropped.
+pub struct ConfigWatcher<T> {
+    handle: ConfigHandle<T>,
+    events: Receiver<ConfigEvent<T>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl<T> ConfigWatcher<T> {
+    /// Returns the handle on the latest configuration
+    pub fn handle(&self) -> ConfigHandle<T> {
+        self.handle.clone()
+    }
+
+    /// Returns the channel receiving an event each time the file has been reloaded, or rejected
+    pub fn events(&self) -> &Receiver<ConfigEvent<T>> {
+        &self.events
+    }
+}
+
+/// Loads the configuration file at `config_file_path`, then watches it to reload it (through
+/// `initialization::load_config`) whenever it changes
+///
+/// The parent directory of the file is watched rather than the file itself, so that the file is
+/// still followed once replaced by a rename, as `initialize_config_file` and most editors do.
+///
+/// # Arguments
+/// * `config_file_path` - The path to the configuration file to watch
+/// * `format` - a `SerializationFormat` value to tell which file format to parse
+/// * `options` - How to reload the file
+///
+/// # Returns
+/// * Ok(ConfigWatcher) holding the loaded configuration
+/// * Any error returned by `initialization::load_config` if the file cannot be loaded
+/// * Err(ConfiggenError::WatchFailed) if the file cannot be watched
+pub fn watch_config<T>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+    options: &WatchOptions,
+) -> Result<ConfigWatcher<T>, ConfiggenError>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    watch_with(config_file_path, format, options, load_config::<T>)
+}
+
+/// Same as `watch_config`, the configuration being validated (through
+/// `initialization::load_validated_config`) before being published : a reloaded configuration
+/// breaking any rule is rejected
+pub fn watch_validated_config<T>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+    options: &WatchOptions,
+) -> Result<ConfigWatcher<T>, ConfiggenError>
+where
+    T: DeserializeOwned + Validate + Send + Sync + 'static,
+{
+    watch_with(
+        config_file_path,
+        format,
+        options,
+        load_validated_config::<T>,
+    )
+}
+
+fn watch_with<T, F>(
+    config_file_path: &Path,
+    format: SerializationFormat,
+    options: &WatchOptions,
+    load: F,
+) -> Result<ConfigWatcher<T>, ConfiggenError>
+where
+    T: Send + Sync + 'static,
+    F: Fn(&Path, SerializationFormat) -> Result<T, ConfiggenError> + Send + 'static,
+{
+    let watch_failed = |e: Box<dyn std::error::Error + Send + Sync>| ConfiggenError::WatchFailed {
+        path: config_file_path.to_path_buf(),
+        source: e,
+    };
+    let handle = ConfigHandle::new(load(config_file_path, format)?);
+
+    let parent = match config_file_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let (notifications_tx, notifications) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(notifications_tx).map_err(|e| watch_failed(Box::new(e)))?;
+    watcher
+        .watch(parent, RecursiveMode::NonRecursive)
+        .map_err(|e| watch_failed(Box::new(e)))?;
+
+    let (events_tx, events) = mpsc::channel();
+    let reloader = Reloader {
+        config_file_path: config_file_path.to_path_buf(),
+        format,
+        debounce: options.debounce,
+        load,
+        handle: handle.clone(),
+        events: events_tx,
+    };
+    std::thread::Builder::new()
+        .name("configgen-watcher".to_owned())
+        .spawn(move || reloader.run(notifications))
+        .map_err(|e| watch_failed(Box::new(e)))?;
+
+    Ok(ConfigWatcher {
+        handle,
+        events,
+        _watcher: watcher,
+    })
+}
+
+/// The background side of a `ConfigWatcher`
+struct Reloader<T, F> {
+    config_file_path: PathBuf,
+    format: SerializationFormat,
+    debounce: Duration,
+    load: F,
+    handle: ConfigHandle<T>,
+    events: Sender<ConfigEvent<T>>,
+}
+
+impl<T, F> Reloader<T, F>
+where
+    F: Fn(&Path, SerializationFormat) -> Result<T, ConfiggenError>,
+{
+    /// Reloads the configuration after each burst of changes, until the watcher is dropped
+    fn run(self, notifications: Receiver<notify::Result<notify::Event>>) {
+        while let Ok(notification) = notifications.recv() {
+            if !self.is_relevant(notification) {
+                continue;
+            }
+            // Wait for the burst of changes to settle, the changes of the other files of the
+            // directory not delaying the reload
+            let mut deadline = Instant::now() + self.debounce;
+            loop {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                match notifications.recv_timeout(timeout) {
+                    Ok(notification) => {
+                        if self.is_relevant(notification) {
+                            deadline = Instant::now() + self.debounce;
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            let event = match (self.load)(&self.config_file_path, self.format) {
+                Ok(config) => {
+                    let config = Arc::new(config);
+                    self.handle.store(config.clone());
+                    ConfigEvent::Reloaded(config)
+                }
+                Err(e) => ConfigEvent::Rejected(e),
+            };
+            // Nobody listening to the events is fine, the handle being updated anyway
+            let _ = self.events.send(event);
+        }
+    }
+
+    /// Tells whether `notification` is about the content of the watched file
+    fn is_relevant(&self, notification: notify::Result<notify::Event>) -> bool {
+        let event = match notification {
+            Ok(event) => event,
+            // Something may have been missed, better reload
+            Err(_) => return true,
+        };
+        let changes_content = matches!(
+            event.kind,
+            EventKind::Any | EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        );
+        changes_content
+            && event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == self.config_file_path.file_name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::initialization::initialize_config_file;
+    use crate::{OverwritePolicy, Violation};
+    use serde::{Deserialize, Serialize};
+    use temp_dir::TempDir;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct AppConfig {
+        pub workers: u32,
+    }
+
+    impl Validate for AppConfig {
+        fn validate(&self) -> Vec<Violation> {
+            match self.workers {
+                0 => vec![Violation::new("workers", "must not be 0")],
+                _ => vec![],
+            }
+        }
+    }
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn get_options() -> WatchOptions {
+        WatchOptions {
+            debounce: Duration::from_millis(50),
+        }
+    }
+
+    fn write(path: &Path, workers: u32) {
+        initialize_config_file(
+            &AppConfig { workers },
+            path,
+            SerializationFormat::Toml,
+            OverwritePolicy::Overwrite,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    pub fn test_watch_config() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("config.toml");
+        write(&path, 1);
+
+        let watcher =
+            watch_config::<AppConfig>(&path, SerializationFormat::Toml, &WatchOptions::default())
+                .unwrap();
+        let handle = watcher.handle();
+        assert_eq!(handle.load().workers, 1);
+
+        // A burst of writes is reloaded once, with the last value
+        for workers in 2..5 {
+            write(&path, workers);
+        }
+        match watcher.events().recv_timeout(TIMEOUT).unwrap() {
+            ConfigEvent::Reloaded(config) => assert_eq!(config.workers, 4),
+            e => panic!("Unexpected event {:?}", e),
+        }
+        assert_eq!(handle.load().workers, 4);
+
+        // Invalid files are rejected, and the previous configuration kept
+        std::fs::write(&path, "workers = \"many\"").unwrap();
+        match watcher.events().recv_timeout(TIMEOUT).unwrap() {
+            ConfigEvent::Rejected(ConfiggenError::LoadingFailed { .. }) => (),
+            e => panic!("Unexpected event {:?}", e),
+        }
+        assert_eq!(handle.load().workers, 4);
+
+        // Other files of the directory are ignored
+        std::fs::write(tmpdir.child("other.toml"), "workers = 5").unwrap();
+        assert!(watcher
+            .events()
+            .recv_timeout(Duration::from_millis(300))
+            .is_err());
+    }
+
+    #[test]
+    pub fn test_other_files_do_not_delay_reload() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("config.toml");
+        write(&path, 1);
+        let watcher =
+            watch_config::<AppConfig>(&path, SerializationFormat::Toml, &get_options()).unwrap();
+
+        // Another file of the directory keeps changing for longer than the reload may take
+        let other = tmpdir.child("other.toml");
+        let writer = std::thread::spawn(move || {
+            for workers in 0..150 {
+                std::fs::write(&other, format!("workers = {}", workers)).unwrap();
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        });
+        write(&path, 2);
+        match watcher.events().recv_timeout(Duration::from_secs(2)) {
+            Ok(ConfigEvent::Reloaded(config)) => assert_eq!(config.workers, 2),
+            e => panic!("Unexpected event {:?}", e),
+        }
+        writer.join().unwrap();
+    }
+
+    #[test]
+    pub fn test_watch_validated_config() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.child("config.toml");
+        write(&path, 0);
+        let r =
+            watch_validated_config::<AppConfig>(&path, SerializationFormat::Toml, &get_options());
+        assert!(matches!(r, Err(ConfiggenError::ValidationFailed { .. })));
+
+        write(&path, 1);
+        let watcher =
+            watch_validated_config::<AppConfig>(&path, SerializationFormat::Toml, &get_options())
+                .unwrap();
+        write(&path, 0);
+        match watcher.events().recv_timeout(TIMEOUT).unwrap() {
+            ConfigEvent::Rejected(ConfiggenError::ValidationFailed { .. }) => (),
+            e => panic!("Unexpected event {:?}", e),
+        }
+        assert_eq!(watcher.handle().load().workers, 1);
+    }
+}
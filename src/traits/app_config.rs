@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::paths::AppPaths;
+use crate::DefaultConfig;
+use crate::Error as ConfiggenError;
+use crate::SerializationFormat;
+
+/// A configuration type that knows its default value and where it is stored, so that
+/// `initialization::initialize` and `initialization::load_or_initialize` can create and load it
+/// without any other argument
+///
+/// Only `APP_NAME` and `default_config` have to be provided :
+/// ```
+/// # use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct MyConfig {
+///     pub workers: u32,
+/// }
+///
+/// impl configgen_rs::AppConfig for MyConfig {
+///     const APP_NAME: &'static str = "myapp";
+///
+///     fn default_config() -> Self {
+///         MyConfig { workers: 4 }
+///     }
+/// }
+/// ```
+pub trait AppConfig: DefaultConfig + Serialize + Sized {
+    /// The name of the application, used to resolve the configuration directory
+    const APP_NAME: &'static str;
+    /// The reverse domain name qualifier of the application (e.g. `com`, `org`), only used on
+    /// macOS
+    const QUALIFIER: &'static str = "";
+    /// The name of the organization developing the application, unused on Linux
+    const ORGANIZATION: &'static str = "";
+    /// The name of the configuration file, without its extension
+    const FILE_NAME: &'static str = "config";
+    /// The format the configuration file is written in
+    const FORMAT: SerializationFormat = SerializationFormat::Toml;
+
+    /// Returns the configuration written into a freshly created configuration file
+    fn default_config() -> Self;
+
+    /// Returns the standard directories of the application
+    fn app_paths() -> AppPaths {
+        AppPaths::new(Self::QUALIFIER, Self::ORGANIZATION, Self::APP_NAME)
+    }
+
+    /// Returns the path of the configuration file, in the configuration directory of the
+    /// application
+    ///
+    /// # Returns
+    /// * Err(ConfiggenError::HomeDirectoryNotFound) if the configuration directory cannot be
+    ///   resolved
+    fn config_file_path() -> Result<PathBuf, ConfiggenError> {
+        Self::app_paths().config_file_path(Self::FILE_NAME, Self::FORMAT)
+    }
+}
@@ -0,0 +1,62 @@
+use std::marker::PhantomData;
+
+/// A configuration type whose fields are documented, the documentation being written as comments
+/// into the generated configuration files
+///
+/// This trait is usually derived from the doc comments of the fields, with
+/// `#[derive(configgen_rs::Documented)]` (`derive` feature).
+pub trait Documented {
+    /// Returns the documentation of the fields, keyed by their dotted path (e.g. `server.port`)
+    ///
+    /// The fields of nested documented structs are included, after the field holding them.
+    fn field_docs() -> Vec<(String, String)>;
+}
+
+impl<T: Documented> Documented for Option<T> {
+    fn field_docs() -> Vec<(String, String)> {
+        T::field_docs()
+    }
+}
+
+impl<T: Documented + ?Sized> Documented for Box<T> {
+    fn field_docs() -> Vec<(String, String)> {
+        T::field_docs()
+    }
+}
+
+// What follows is used by the derive macro to collect the documentation of the nested structs
+// that implement `Documented`, while ignoring the field types that do not : the method is looked
+// up on `&NestedDocs<T>`, which matches `DocumentedNested` as is, and `UndocumentedNested` only
+// after an auto-reference, so the former wins whenever `T: Documented`.
+
+#[doc(hidden)]
+pub struct NestedDocs<T: ?Sized>(PhantomData<T>);
+
+impl<T: ?Sized> NestedDocs<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        NestedDocs(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait DocumentedNested {
+    fn nested_docs(&self) -> Vec<(String, String)>;
+}
+
+impl<T: Documented + ?Sized> DocumentedNested for NestedDocs<T> {
+    fn nested_docs(&self) -> Vec<(String, String)> {
+        T::field_docs()
+    }
+}
+
+#[doc(hidden)]
+pub trait UndocumentedNested {
+    fn nested_docs(&self) -> Vec<(String, String)>;
+}
+
+impl<T: ?Sized> UndocumentedNested for &NestedDocs<T> {
+    fn nested_docs(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+}
@@ -1,3 +1,9 @@
+pub mod app_config;
 pub mod default_config;
+pub mod documented;
+pub mod validate;
 
+pub use app_config::AppConfig;
 pub use default_config::DefaultConfig;
+pub use documented::Documented;
+pub use validate::{Validate, Violation};
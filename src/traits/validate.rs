@@ -0,0 +1,69 @@
+use std::fmt;
+
+/// A rule of the configuration broken by a value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// The dotted path of the offending value (e.g. `server.port`), empty if the violation is
+    /// about the whole configuration
+    pub path: String,
+    /// What is wrong with the value
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Violation {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Returns the violation with its path prefixed by `prefix`, to report the violations of a
+    /// nested struct from the point of view of the struct holding it
+    pub fn prefixed(self, prefix: &str) -> Self {
+        let path = match self.path.is_empty() {
+            true => prefix.to_owned(),
+            false => crate::value::join_path(prefix, &self.path),
+        };
+        Violation {
+            path,
+            message: self.message,
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path.is_empty() {
+            true => write!(f, "{}", self.message),
+            false => write!(f, "`{}` {}", self.path, self.message),
+        }
+    }
+}
+
+/// A configuration type that can check its values, before being written by
+/// `initialization::initialize_validated_config_file` and after being loaded by
+/// `initialization::load_validated_config`
+///
+/// The `_validated` variants of `load_or_init` and `load_or_initialize` do both. The plain entry
+/// points never validate, since they do not require `Validate` of the configuration type.
+pub trait Validate {
+    /// Returns all the rules broken by the configuration, none if it is valid
+    fn validate(&self) -> Vec<Violation>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_violation() {
+        let violation = Violation::new("port", "must be above 1024").prefixed("server");
+        assert_eq!(violation.path, "server.port");
+        assert_eq!(violation.to_string(), "`server.port` must be above 1024");
+
+        let violation = Violation::new("", "no server is enabled");
+        assert_eq!(violation.to_string(), "no server is enabled");
+        assert_eq!(violation.prefixed("servers").path, "servers");
+    }
+}
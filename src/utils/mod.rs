@@ -1,10 +1,105 @@
-use std::path::PathBuf;
-
-pub fn read_configuration(config_file_path: &PathBuf) -> String {
-    let file_to_read = std::fs::File::open(config_file_path).unwrap();
-    let mut reader = std::io::BufReader::new(file_to_read);
-    let mut buf = String::new();
-    let r = std::io::Read::read_to_string(&mut reader, &mut buf);
-    assert!(r.is_ok());
-    buf
+use std::path::Path;
+
+use config::{File, FileFormat, FileSourceFile};
+use serde_json::Value;
+
+use crate::formats;
+use crate::value;
+use crate::Error as ConfiggenError;
+use crate::SerializationFormat;
+
+/// Reads the whole content of the configuration file at `config_file_path`
+///
+/// # Returns
+/// * Ok(String) containing the content of the file
+/// * Err(ConfiggenError::ReadingFailed) if the file cannot be opened, read, or is not valid UTF-8
+pub fn read_configuration(config_file_path: &Path) -> Result<String, ConfiggenError> {
+    std::fs::read_to_string(config_file_path).map_err(|e| ConfiggenError::ReadingFailed {
+        path: config_file_path.to_path_buf(),
+        source: e,
+    })
+}
+
+/// Reads the configuration file at `config_file_path` into a format-neutral value tree, to inspect
+/// or edit a configuration without knowing its type
+///
+/// The `$schema` key of JSON and JSON5 files, which is not part of the configuration, is left out.
+///
+/// # Returns
+/// * Ok(Value) if the file could be read and parsed
+/// * Err(ConfiggenError::ReadingFailed) if the file could not be read
+/// * Err(ConfiggenError::ParsingFailed) if the content of the file is not valid in `format`
+pub fn read_value(
+    config_file_path: &Path,
+    format: SerializationFormat,
+) -> Result<Value, ConfiggenError> {
+    formats::read_config_value(config_file_path, format)
+}
+
+/// Serializes a format-neutral value tree, such as one returned by `read_value`, in the given
+/// `format`
+///
+/// # Returns
+/// * Ok(String) containing the serialized value
+/// * Err(ConfiggenError::SerializationFailed) if the value cannot be represented in `format`
+pub fn value_to_string(
+    value: &Value,
+    format: SerializationFormat,
+) -> Result<String, ConfiggenError> {
+    formats::value_to_string(value, format)
+}
+
+/// Returns the dotted paths of the keys of the value tree `default` that `config` lacks (e.g.
+/// `server.timeout`), the ones whose default value is `null` apart
+pub fn missing_keys(config: &Value, default: &Value) -> Vec<String> {
+    value::merge_missing(&mut config.clone(), default)
+}
+
+/// Builds a `config::File` source reading the file at `config_file_path` in the given `format`,
+/// typically one written by `initialization::initialize_config_file`
+///
+/// # Example
+/// ```no_run
+/// # use std::path::Path;
+/// let source = configgen_rs::utils::config_file_source(
+///     Path::new("/tmp/config.toml"),
+///     configgen_rs::SerializationFormat::Toml,
+/// );
+/// let config = config::Config::builder().add_source(source).build();
+/// ```
+pub fn config_file_source(
+    config_file_path: &Path,
+    format: SerializationFormat,
+) -> File<FileSourceFile, FileFormat> {
+    File::from(config_file_path).format(format.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use temp_dir::TempDir;
+
+    #[test]
+    pub fn test_missing_keys() {
+        let config = serde_json::json!({"name": "mine", "server": {"port": 9090}});
+        let default = serde_json::json!({
+            "name": "app",
+            "server": {"port": 8080, "host": "localhost"},
+            "tags": [],
+            "timeout": null,
+        });
+        assert_eq!(missing_keys(&config, &default), vec!["server.host", "tags"]);
+    }
+
+    #[test]
+    pub fn test_read_configuration() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("config.toml");
+        std::fs::write(&path, "toto = 2").unwrap();
+
+        assert_eq!(read_configuration(&path).unwrap(), "toto = 2");
+
+        let r = read_configuration(&tmpdir.path().join("missing.toml"));
+        assert!(matches!(r, Err(ConfiggenError::ReadingFailed { .. })));
+    }
 }
@@ -0,0 +1,80 @@
+use serde_json::Value;
+
+/// Recursively adds to `target` the keys of `defaults` it lacks, leaving the existing values
+/// untouched
+///
+/// Only tables are merged : if a key holds a table in `defaults` but something else in `target`,
+/// the value of `target` wins. The `null` defaults are not added, since a missing key deserializes
+/// into `None` anyway, and TOML and INI cannot hold them.
+///
+/// # Returns
+/// * The dotted paths of the keys that have been added (e.g. `server.timeout`), in the order of
+///   `defaults`
+pub fn merge_missing(target: &mut Value, defaults: &Value) -> Vec<String> {
+    let mut added = vec![];
+    merge_missing_at("", target, defaults, &mut added);
+    added
+}
+
+fn merge_missing_at(prefix: &str, target: &mut Value, defaults: &Value, added: &mut Vec<String>) {
+    let (target, defaults) = match (target, defaults) {
+        (Value::Object(t), Value::Object(d)) => (t, d),
+        _ => return,
+    };
+
+    for (key, default) in defaults.iter() {
+        let path = join_path(prefix, key);
+        match target.get_mut(key) {
+            Some(existing) => merge_missing_at(&path, existing, default, added),
+            None if default.is_null() => (),
+            None => {
+                target.insert(key.clone(), default.clone());
+                added.push(path);
+            }
+        }
+    }
+}
+
+/// Appends `key` to the dotted path `prefix`
+pub fn join_path(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    pub fn test_merge_missing() {
+        let mut target = json!({
+            "port": 9000,
+            "server": { "host": "example.com" },
+            "tags": ["a"],
+        });
+        let defaults = json!({
+            "port": 8080,
+            "server": { "host": "localhost", "timeout": 30 },
+            "tags": ["b", "c"],
+            "verbose": false,
+            "timeout": null,
+        });
+
+        let added = merge_missing(&mut target, &defaults);
+        assert_eq!(added, vec!["server.timeout", "verbose"]);
+        assert_eq!(
+            target,
+            json!({
+                "port": 9000,
+                "server": { "host": "example.com", "timeout": 30 },
+                "tags": ["a"],
+                "verbose": false,
+            })
+        );
+        assert!(merge_missing(&mut target, &defaults).is_empty());
+    }
+}
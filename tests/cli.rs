@@ -0,0 +1,257 @@
+//! End-to-end tests of the `configgen` binary, checking its output and exit codes
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use serde_json::{json, Value};
+use temp_dir::TempDir;
+
+const DEFAULT: &str =
+    "name = \"app\"\nwhen = 1979-05-27T07:32:00Z\n\n[server]\nhost = \"localhost\"\nport = 8080\n";
+
+fn configgen(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_configgen"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn path(tmpdir: &TempDir, name: &str) -> String {
+    tmpdir.child(name).display().to_string()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn json_stdout(output: &Output) -> Value {
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+fn write_default(tmpdir: &TempDir) -> String {
+    let default = path(tmpdir, "default.toml");
+    std::fs::write(&default, DEFAULT).unwrap();
+    default
+}
+
+#[test]
+pub fn test_init() {
+    let tmpdir = TempDir::new().unwrap();
+    let default = write_default(&tmpdir);
+    let config = path(&tmpdir, "config.json");
+
+    let output = configgen(&["init", &config, "--from", &default]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), format!("Created {}\n", config));
+    assert_eq!(
+        std::fs::read_to_string(&config).unwrap(),
+        r#"{"name":"app","when":"1979-05-27T07:32:00Z","server":{"host":"localhost","port":8080}}"#
+    );
+
+    let output = configgen(&["init", &config, "--from", &default, "--json"]);
+    assert_eq!(output.status.code(), Some(73));
+    let report = json_stdout(&output);
+    assert_eq!(report["ok"], false);
+    assert_eq!(report["error"]["kind"], "ConfigFileAlreadyExists");
+
+    let output = configgen(&[
+        "--json", "init", &config, "--from", &default, "--policy", "merge",
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        json_stdout(&output),
+        json!({ "ok": true, "path": config, "outcome": "skipped", "backup": null })
+    );
+
+    // A template whose extension does not tell its format
+    let template = path(&tmpdir, "template.conf");
+    std::fs::copy(&default, &template).unwrap();
+    let output = configgen(&[
+        "--json",
+        "init",
+        &config,
+        "--from",
+        &template,
+        "--from-format",
+        "toml",
+        "--policy",
+        "merge",
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        json_stdout(&output),
+        json!({ "ok": true, "path": config, "outcome": "skipped", "backup": null })
+    );
+}
+
+#[test]
+pub fn test_show() {
+    let tmpdir = TempDir::new().unwrap();
+    let default = write_default(&tmpdir);
+
+    let output = configgen(&["show", &default, "--to", "json"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "{\"name\":\"app\",\"when\":\"1979-05-27T07:32:00Z\",\"server\":{\"host\":\"localhost\",\"port\":8080}}\n"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_configgen"))
+        .args(["show", &default, "--env", "CONFIGGEN_TEST", "--json"])
+        .env("CONFIGGEN_TEST__SERVER__PORT", "9090")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let report = json_stdout(&output);
+    assert_eq!(report["ok"], true);
+    assert_eq!(report["config"]["server"]["port"], 9090);
+    assert_eq!(report["overridden"], json!(["server.port"]));
+}
+
+#[test]
+pub fn test_validate() {
+    let tmpdir = TempDir::new().unwrap();
+    let default = write_default(&tmpdir);
+    let config = path(&tmpdir, "config.yaml");
+    std::fs::write(&config, "name: mine\nserver:\n  port: 9090\n").unwrap();
+
+    let output = configgen(&["validate", &config, "--against", &default, "--json"]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        json_stdout(&output),
+        json!({ "ok": true, "valid": false, "missing": ["when", "server.host"] })
+    );
+
+    let output = configgen(&["validate", &default]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), format!("{} is valid\n", default));
+}
+
+#[test]
+pub fn test_convert() {
+    let tmpdir = TempDir::new().unwrap();
+    let default = write_default(&tmpdir);
+    let output_path = path(&tmpdir, "config.yaml");
+
+    let output = configgen(&["convert", &default, &output_path, "--json"]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        json_stdout(&output)["error"]["values"],
+        json!([{ "path": "when", "message": "is a TOML datetime" }])
+    );
+
+    std::fs::write(
+        &default,
+        DEFAULT.replace("when = 1979-05-27T07:32:00Z\n", ""),
+    )
+    .unwrap();
+    let output = configgen(&["convert", &default, &output_path, "--json"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        json_stdout(&output),
+        json!({ "ok": true, "path": output_path, "format": "yaml", "written": true })
+    );
+    assert_eq!(
+        std::fs::read_to_string(&output_path).unwrap(),
+        "name: app\nserver:\n  host: localhost\n  port: 8080\n"
+    );
+
+    let input = path(&tmpdir, "list.json");
+    std::fs::write(&input, r#"{"tags": ["a", null]}"#).unwrap();
+    let output = configgen(&["convert", &input, &path(&tmpdir, "list.toml"), "--json"]);
+    assert_eq!(output.status.code(), Some(65));
+    let report = json_stdout(&output);
+    assert_eq!(report["error"]["kind"], "UnrepresentableValues");
+    assert_eq!(
+        report["error"]["values"],
+        json!([{ "path": "tags[1]", "message": "is null" }])
+    );
+    assert!(!Path::new(&path(&tmpdir, "list.toml")).exists());
+}
+
+#[test]
+pub fn test_diff() {
+    let tmpdir = TempDir::new().unwrap();
+    let default = write_default(&tmpdir);
+    let config = path(&tmpdir, "config.toml");
+    std::fs::write(
+        &config,
+        DEFAULT.replace("port = 8080", "port = 9090\ntimeout = 30"),
+    )
+    .unwrap();
+
+    let output = configgen(&["diff", &default, &config]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "~ server.port = 8080 -> 9090\n+ server.timeout = 30\n"
+    );
+
+    let output = configgen(&["diff", &default, &config, "--json"]);
+    assert_eq!(output.status.code(), Some(1));
+    let report = json_stdout(&output);
+    assert_eq!(
+        report["changes"],
+        json!([
+            { "op": "changed", "path": "server.port", "old": 8080, "new": 9090 },
+            { "op": "added", "path": "server.timeout", "new": 30 },
+        ])
+    );
+    assert_eq!(report["patch"][0]["path"], "/server/port");
+
+    // Identical files do not differ
+    let output = configgen(&["diff", &default, &default]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+
+    // Files whose extension does not tell their format
+    let default_conf = path(&tmpdir, "default.conf");
+    let config_conf = path(&tmpdir, "config");
+    std::fs::copy(&default, &default_conf).unwrap();
+    std::fs::copy(&config, &config_conf).unwrap();
+    let output = configgen(&["diff", &default_conf, &config_conf]);
+    assert_eq!(output.status.code(), Some(64));
+    let output = configgen(&[
+        "diff",
+        &default_conf,
+        &config_conf,
+        "--default-format",
+        "toml",
+        "--format",
+        "toml",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "~ server.port = 8080 -> 9090\n+ server.timeout = 30\n"
+    );
+
+    // The datetimes are shown as plain strings
+    std::fs::write(&config, DEFAULT.replace("1979", "2000")).unwrap();
+    let output = configgen(&["diff", &default, &config]);
+    assert_eq!(
+        stdout(&output),
+        "~ when = \"1979-05-27T07:32:00Z\" -> \"2000-05-27T07:32:00Z\"\n"
+    );
+}
+
+#[test]
+pub fn test_errors() {
+    let tmpdir = TempDir::new().unwrap();
+
+    let output = configgen(&["show", &path(&tmpdir, "missing.toml"), "--json"]);
+    assert_eq!(output.status.code(), Some(66));
+    assert_eq!(json_stdout(&output)["error"]["kind"], "ReadingFailed");
+
+    let output = configgen(&["show", &path(&tmpdir, "config.xml")]);
+    assert_eq!(output.status.code(), Some(64));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("configgen: "));
+
+    let invalid = path(&tmpdir, "invalid.json");
+    std::fs::write(&invalid, "{").unwrap();
+    let output = configgen(&["show", &invalid, "--json"]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(json_stdout(&output)["error"]["kind"], "ParsingFailed");
+}